@@ -0,0 +1,146 @@
+use crate::admin_module::ensure_admin;
+use crate::constants::PRICE_SCALE_FACTOR;
+use crate::player_module::Player;
+use spacetimedb::sats::u256;
+use spacetimedb::{reducer, table, Identity, ReducerContext, SpacetimeType, Table, Timestamp};
+
+#[table(name = player_stats, public)]
+pub struct PlayerStats {
+    #[primary_key]
+    pub identity: Identity,
+    pub total_clicks: u64,
+    pub lifetime_earnings: u256,
+    pub trades_executed: u64,
+    pub biggest_single_profit: i128,
+    pub upgrades_bought: u64,
+}
+
+/// Money thresholds are unscaled, like upgrade costs.
+#[derive(SpacetimeType, Clone, PartialEq)]
+pub enum AchievementCriterion {
+    TotalClicks(u64),
+    LifetimeEarnings(u128),
+    TradesExecuted(u64),
+    BiggestSingleProfit(u128),
+    UpgradesBought(u64),
+}
+
+#[table(name = achievement, public)]
+pub struct Achievement {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u16,
+    #[unique]
+    pub identifier: String,
+    pub title: String,
+    pub description: String,
+    pub criterion: AchievementCriterion,
+    pub reward_money: u128,
+}
+
+#[table(name = player_achievement, public)]
+pub struct PlayerAchievement {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    #[index(btree)]
+    pub identity: Identity,
+    pub achievement_id: u16,
+    pub unlocked_at: Timestamp,
+}
+
+#[reducer]
+pub fn add_achievement(
+    ctx: &ReducerContext,
+    identifier: String,
+    title: String,
+    description: String,
+    criterion: AchievementCriterion,
+    reward_money: u128,
+) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    ctx.db
+        .achievement()
+        .try_insert(Achievement {
+            id: 0,
+            identifier,
+            title,
+            description,
+            criterion,
+            reward_money,
+        })
+        .map_err(|_| "Achievement identifier already exists.".to_string())?;
+    Ok(())
+}
+
+/// Applies `update` to the player's stats and unlocks any achievements it satisfies.
+/// Rewards are credited to `player`; the caller is responsible for persisting the row.
+pub fn record_stats(ctx: &ReducerContext, player: &mut Player, update: impl FnOnce(&mut PlayerStats)) {
+    let existing = ctx.db.player_stats().identity().find(player.identity);
+    let is_new = existing.is_none();
+    let mut stats = existing.unwrap_or(PlayerStats {
+        identity: player.identity,
+        total_clicks: 0,
+        lifetime_earnings: u256::new(0),
+        trades_executed: 0,
+        biggest_single_profit: 0,
+        upgrades_bought: 0,
+    });
+
+    update(&mut stats);
+    grant_achievements(ctx, player, &stats);
+
+    if is_new {
+        ctx.db.player_stats().insert(stats);
+    } else {
+        ctx.db.player_stats().identity().update(stats);
+    }
+}
+
+pub fn total_clicks(ctx: &ReducerContext, identity: Identity) -> u64 {
+    ctx.db
+        .player_stats()
+        .identity()
+        .find(identity)
+        .map_or(0, |s| s.total_clicks)
+}
+
+fn grant_achievements(ctx: &ReducerContext, player: &mut Player, stats: &PlayerStats) {
+    let unlocked: Vec<u16> = ctx
+        .db
+        .player_achievement()
+        .identity()
+        .filter(player.identity)
+        .map(|a| a.achievement_id)
+        .collect();
+
+    for achievement in ctx.db.achievement().iter() {
+        if unlocked.contains(&achievement.id) || !criterion_met(&achievement.criterion, stats) {
+            continue;
+        }
+
+        ctx.db.player_achievement().insert(PlayerAchievement {
+            id: 0,
+            identity: player.identity,
+            achievement_id: achievement.id,
+            unlocked_at: ctx.timestamp,
+        });
+        player.money += u256::from(achievement.reward_money) * u256::from(PRICE_SCALE_FACTOR);
+    }
+}
+
+fn criterion_met(criterion: &AchievementCriterion, stats: &PlayerStats) -> bool {
+    let scaled = |money: u128| u256::from(money) * u256::from(PRICE_SCALE_FACTOR);
+
+    match criterion {
+        AchievementCriterion::TotalClicks(target) => stats.total_clicks >= *target,
+        AchievementCriterion::LifetimeEarnings(target) => stats.lifetime_earnings >= scaled(*target),
+        AchievementCriterion::TradesExecuted(target) => stats.trades_executed >= *target,
+        AchievementCriterion::BiggestSingleProfit(target) => {
+            stats.biggest_single_profit > 0
+                && u256::from(stats.biggest_single_profit as u128) >= scaled(*target)
+        }
+        AchievementCriterion::UpgradesBought(target) => stats.upgrades_bought >= *target,
+    }
+}
@@ -0,0 +1,66 @@
+use spacetimedb::{reducer, table, Identity, ReducerContext, Table, Timestamp};
+
+#[table(name = admin, public)]
+pub struct Admin {
+    #[primary_key]
+    pub identity: Identity,
+    pub granted_by: Identity,
+    pub granted_at: Timestamp,
+}
+
+pub fn init_admin(ctx: &ReducerContext) -> Result<(), String> {
+    if ctx.db.admin().identity().find(ctx.sender).is_none() {
+        ctx.db.admin().insert(Admin {
+            identity: ctx.sender,
+            granted_by: ctx.sender,
+            granted_at: ctx.timestamp,
+        });
+    }
+
+    Ok(())
+}
+
+pub fn is_admin(ctx: &ReducerContext, identity: Identity) -> bool {
+    ctx.db.admin().identity().find(identity).is_some()
+}
+
+/// Scheduled reducers run as the module itself, so the module identity is always allowed.
+pub fn ensure_admin(ctx: &ReducerContext) -> Result<(), String> {
+    if ctx.sender == ctx.identity() || is_admin(ctx, ctx.sender) {
+        Ok(())
+    } else {
+        Err("Only admins can perform this action.".to_string())
+    }
+}
+
+#[reducer]
+pub fn grant_admin(ctx: &ReducerContext, identity: Identity) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    if is_admin(ctx, identity) {
+        return Err("Identity is already an admin.".to_string());
+    }
+
+    ctx.db.admin().insert(Admin {
+        identity,
+        granted_by: ctx.sender,
+        granted_at: ctx.timestamp,
+    });
+    Ok(())
+}
+
+#[reducer]
+pub fn revoke_admin(ctx: &ReducerContext, identity: Identity) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    if !is_admin(ctx, identity) {
+        return Err("Identity is not an admin.".to_string());
+    }
+
+    if ctx.db.admin().count() <= 1 {
+        return Err("Cannot revoke the last admin.".to_string());
+    }
+
+    ctx.db.admin().identity().delete(identity);
+    Ok(())
+}
@@ -0,0 +1,260 @@
+use crate::admin_module::ensure_admin;
+use crate::constants::{DECIMAL_SCALE_FACTOR, PLAYER_MIN_CLICK_INTERVAL_MICROS};
+use crate::player_module::Player;
+use spacetimedb::{reducer, table, Identity, ReducerContext, SpacetimeType, Table, TimeDuration, Timestamp};
+
+#[derive(SpacetimeType, PartialEq, Clone, Copy)]
+pub enum AntiCheatAction {
+    FlagOnly,
+    SoftPenalty,
+    TempBan,
+}
+
+#[derive(SpacetimeType, PartialEq, Clone, Copy)]
+pub enum ClickFlagReason {
+    LowIntervalVariance,
+    SustainedMaxRate,
+}
+
+/// `max_rate_ratio` and `penalty_multiplier` are scaled by `DECIMAL_SCALE_FACTOR`.
+#[table(name = anti_cheat_config, public)]
+pub struct AntiCheatConfig {
+    pub sample_size: u32,
+    pub min_interval_stddev_micros: i64,
+    pub rate_window_micros: i64,
+    pub max_rate_ratio: u64,
+    pub action: AntiCheatAction,
+    pub penalty_multiplier: u64,
+    pub penalty_duration_micros: i64,
+    pub ban_duration_micros: i64,
+}
+
+#[table(name = click_tracker)]
+pub struct ClickTracker {
+    #[primary_key]
+    pub identity: Identity,
+    pub recent_intervals: Vec<i64>,
+    pub window_start: Timestamp,
+    pub window_clicks: u64,
+    pub window_max_clicks: u64,
+    pub penalty_until: Option<Timestamp>,
+    pub banned_until: Option<Timestamp>,
+}
+
+#[table(name = click_audit, public)]
+pub struct ClickAudit {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    #[index(btree)]
+    pub identity: Identity,
+    pub reason: ClickFlagReason,
+    pub action: AntiCheatAction,
+    pub interval_mean_micros: i64,
+    pub interval_stddev_micros: i64,
+    pub flagged_at: Timestamp,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn init_anti_cheat_config(
+    ctx: &ReducerContext,
+    sample_size: u32,
+    min_interval_stddev_micros: i64,
+    rate_window_micros: i64,
+    max_rate_ratio: u64,
+    action: AntiCheatAction,
+    penalty_multiplier: u64,
+    penalty_duration_micros: i64,
+    ban_duration_micros: i64,
+) -> Result<(), String> {
+    if sample_size < 2 {
+        return Err("Sample size must be at least 2.".to_string());
+    }
+
+    ctx.db.anti_cheat_config().insert(AntiCheatConfig {
+        sample_size,
+        min_interval_stddev_micros,
+        rate_window_micros,
+        max_rate_ratio,
+        action,
+        penalty_multiplier,
+        penalty_duration_micros,
+        ban_duration_micros,
+    });
+
+    Ok(())
+}
+
+#[reducer]
+#[allow(clippy::too_many_arguments)]
+pub fn set_anti_cheat_config(
+    ctx: &ReducerContext,
+    sample_size: u32,
+    min_interval_stddev_micros: i64,
+    rate_window_micros: i64,
+    max_rate_ratio: u64,
+    action: AntiCheatAction,
+    penalty_multiplier: u64,
+    penalty_duration_micros: i64,
+    ban_duration_micros: i64,
+) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    for config in ctx.db.anti_cheat_config().iter() {
+        ctx.db.anti_cheat_config().delete(config);
+    }
+
+    init_anti_cheat_config(
+        ctx,
+        sample_size,
+        min_interval_stddev_micros,
+        rate_window_micros,
+        max_rate_ratio,
+        action,
+        penalty_multiplier,
+        penalty_duration_micros,
+        ban_duration_micros,
+    )
+}
+
+#[reducer]
+pub fn clear_click_penalty(ctx: &ReducerContext, identity: Identity) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    let Some(mut tracker) = ctx.db.click_tracker().identity().find(identity) else {
+        return Err("No click history for this identity.".to_string());
+    };
+
+    tracker.penalty_until = None;
+    tracker.banned_until = None;
+    ctx.db.click_tracker().identity().update(tracker);
+    Ok(())
+}
+
+pub fn ensure_not_banned(ctx: &ReducerContext, identity: Identity) -> Result<(), String> {
+    let banned = ctx
+        .db
+        .click_tracker()
+        .identity()
+        .find(identity)
+        .and_then(|t| t.banned_until)
+        .is_some_and(|until| ctx.timestamp < until);
+
+    if banned {
+        Err("Clicking is temporarily disabled for this account.".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// Records an accepted click and returns the reward multiplier, scaled by
+/// `DECIMAL_SCALE_FACTOR`, that should apply to it. Must run before `last_click` is updated.
+/// `cooldown_micros` is the cooldown actually enforced for this click.
+pub fn inspect_click(ctx: &ReducerContext, player: &Player, cooldown_micros: i64) -> u128 {
+    let Some(config) = ctx.db.anti_cheat_config().iter().next() else {
+        return DECIMAL_SCALE_FACTOR;
+    };
+
+    let existing = ctx.db.click_tracker().identity().find(player.identity);
+    let is_new = existing.is_none();
+    let mut tracker = existing.unwrap_or(ClickTracker {
+        identity: player.identity,
+        recent_intervals: Vec::new(),
+        window_start: ctx.timestamp,
+        window_clicks: 0,
+        window_max_clicks: 0,
+        penalty_until: None,
+        banned_until: None,
+    });
+
+    if let Some(interval) = ctx.timestamp.time_duration_since(player.last_click) {
+        tracker.recent_intervals.push(interval.to_micros());
+        let overflow = tracker.recent_intervals.len().saturating_sub(config.sample_size as usize);
+        tracker.recent_intervals.drain(..overflow);
+    }
+
+    tracker.window_clicks += 1;
+    tracker.window_max_clicks = tracker.window_max_clicks.max(
+        (config.rate_window_micros / cooldown_micros.max(PLAYER_MIN_CLICK_INTERVAL_MICROS)).max(1)
+            as u64,
+    );
+
+    let (mean, stddev) = interval_stats(&tracker.recent_intervals);
+    if tracker.recent_intervals.len() >= config.sample_size as usize
+        && stddev < config.min_interval_stddev_micros
+    {
+        flag(ctx, &config, &mut tracker, ClickFlagReason::LowIntervalVariance, mean, stddev);
+        tracker.recent_intervals.clear();
+    }
+
+    let window_elapsed = ctx
+        .timestamp
+        .time_duration_since(tracker.window_start)
+        .map_or(0, |d| d.to_micros());
+    if window_elapsed >= config.rate_window_micros {
+        let rate = tracker.window_clicks as u128 * DECIMAL_SCALE_FACTOR / tracker.window_max_clicks as u128;
+        if rate >= config.max_rate_ratio as u128 {
+            flag(ctx, &config, &mut tracker, ClickFlagReason::SustainedMaxRate, mean, stddev);
+        }
+        tracker.window_start = ctx.timestamp;
+        tracker.window_clicks = 0;
+        tracker.window_max_clicks = 0;
+    }
+
+    let penalized = tracker.penalty_until.is_some_and(|until| ctx.timestamp < until);
+
+    if is_new {
+        ctx.db.click_tracker().insert(tracker);
+    } else {
+        ctx.db.click_tracker().identity().update(tracker);
+    }
+
+    if penalized {
+        config.penalty_multiplier as u128
+    } else {
+        DECIMAL_SCALE_FACTOR
+    }
+}
+
+fn flag(
+    ctx: &ReducerContext,
+    config: &AntiCheatConfig,
+    tracker: &mut ClickTracker,
+    reason: ClickFlagReason,
+    mean: i64,
+    stddev: i64,
+) {
+    let until = |micros: i64| ctx.timestamp.checked_add(TimeDuration::from_micros(micros));
+    match config.action {
+        AntiCheatAction::FlagOnly => {}
+        AntiCheatAction::SoftPenalty => tracker.penalty_until = until(config.penalty_duration_micros),
+        AntiCheatAction::TempBan => tracker.banned_until = until(config.ban_duration_micros),
+    }
+
+    log::warn!("Flagged {} for suspicious clicking", tracker.identity);
+    ctx.db.click_audit().insert(ClickAudit {
+        id: 0,
+        identity: tracker.identity,
+        reason,
+        action: config.action,
+        interval_mean_micros: mean,
+        interval_stddev_micros: stddev,
+        flagged_at: ctx.timestamp,
+    });
+}
+
+fn interval_stats(intervals: &[i64]) -> (i64, i64) {
+    if intervals.is_empty() {
+        return (0, 0);
+    }
+
+    let count = intervals.len() as i128;
+    let mean = intervals.iter().map(|&i| i as i128).sum::<i128>() / count;
+    let variance = intervals
+        .iter()
+        .map(|&i| (i as i128 - mean).pow(2))
+        .sum::<i128>()
+        / count;
+
+    (mean as i64, (variance as u128).isqrt() as i64)
+}
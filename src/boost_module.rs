@@ -0,0 +1,175 @@
+use crate::admin_module::ensure_admin;
+use crate::constants::{BOOST_MAX_COOLDOWN_REDUCTION, DECIMAL_SCALE_FACTOR, PRICE_SCALE_FACTOR};
+use crate::player_module::{player, settle_income};
+use crate::portfolio_module::revalue_player;
+use spacetimedb::sats::u256;
+use spacetimedb::{reducer, table, Identity, ReducerContext, SpacetimeType, Table, TimeDuration, Timestamp};
+
+/// Multipliers are scaled by `DECIMAL_SCALE_FACTOR`, so `2_000` doubles the stat, and a
+/// cooldown reduction of `500` halves the click timer. Bonuses from overlapping boosts add
+/// together; cooldown reductions are capped at `BOOST_MAX_COOLDOWN_REDUCTION`.
+#[derive(SpacetimeType, Clone, Copy, PartialEq)]
+pub enum BoostEffect {
+    PassiveIncomeMultiplier(u64),
+    ClickPowerMultiplier(u64),
+    ClickCooldownReduction(u64),
+}
+
+#[table(name = boost, public)]
+pub struct Boost {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u16,
+    #[unique]
+    pub identifier: String,
+    pub title: String,
+    pub description: String,
+    pub cost: u128,
+    pub duration_micros: i64,
+    pub effect: BoostEffect,
+}
+
+#[table(name = active_boost, public)]
+pub struct ActiveBoost {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    #[index(btree)]
+    pub identity: Identity,
+    pub boost_id: u16,
+    pub effect: BoostEffect,
+    pub started_at: Timestamp,
+    pub expires_at: Timestamp,
+}
+
+#[reducer]
+pub fn add_boost(
+    ctx: &ReducerContext,
+    identifier: String,
+    title: String,
+    description: String,
+    cost: u128,
+    duration_micros: i64,
+    effect: BoostEffect,
+) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    if duration_micros <= 0 {
+        return Err("Boost duration must be positive.".to_string());
+    }
+
+    ctx.db
+        .boost()
+        .try_insert(Boost {
+            id: 0,
+            identifier,
+            title,
+            description,
+            cost,
+            duration_micros,
+            effect,
+        })
+        .map_err(|_| "Boost identifier already exists.".to_string())?;
+    Ok(())
+}
+
+#[reducer]
+pub fn buy_boost(ctx: &ReducerContext, boost_id: u16) -> Result<(), String> {
+    let Some(mut player) = ctx.db.player().identity().find(ctx.sender) else {
+        return Err("Player not found".to_string());
+    };
+
+    let Some(boost) = ctx.db.boost().id().find(boost_id) else {
+        return Err("Boost not found".to_string());
+    };
+
+    settle_income(ctx, &mut player);
+
+    let scaled_cost = u256::from(boost.cost)
+        .checked_mul(u256::from(PRICE_SCALE_FACTOR))
+        .ok_or("Overflow calculating boost cost.")?;
+
+    if player.money < scaled_cost {
+        return Err("Not enough money".to_string());
+    }
+
+    let expires_at = ctx
+        .timestamp
+        .checked_add(TimeDuration::from_micros(boost.duration_micros))
+        .ok_or("Failed to calculate boost expiry.")?;
+
+    player.money -= scaled_cost;
+    ctx.db.active_boost().insert(ActiveBoost {
+        id: 0,
+        identity: ctx.sender,
+        boost_id,
+        effect: boost.effect,
+        started_at: ctx.timestamp,
+        expires_at,
+    });
+
+    revalue_player(ctx, &mut player);
+    ctx.db.player().identity().update(player);
+    Ok(())
+}
+
+/// Extra passive-income time, in micros scaled by `DECIMAL_SCALE_FACTOR`, earned from
+/// passive boosts overlapping `[from, to)`. Boosts that have fully elapsed are removed.
+pub fn passive_boost_bonus(
+    ctx: &ReducerContext,
+    identity: Identity,
+    from: Timestamp,
+    to: Timestamp,
+) -> u256 {
+    let mut bonus = u256::new(0);
+    let mut expired = Vec::new();
+
+    for active in ctx.db.active_boost().identity().filter(identity) {
+        if active.expires_at <= to {
+            expired.push(active.id);
+        }
+
+        let BoostEffect::PassiveIncomeMultiplier(multiplier) = active.effect else {
+            continue;
+        };
+
+        let start = active.started_at.max(from);
+        let end = active.expires_at.min(to);
+        if let Some(overlap) = end.time_duration_since(start) {
+            let extra = (multiplier as u128).saturating_sub(DECIMAL_SCALE_FACTOR);
+            bonus += u256::from(overlap.to_micros().max(0) as u64) * u256::from(extra);
+        }
+    }
+
+    for id in expired {
+        ctx.db.active_boost().id().delete(id);
+    }
+    bonus
+}
+
+/// Returns the click multiplier and the fraction of the click cooldown currently removed,
+/// both scaled by `DECIMAL_SCALE_FACTOR`.
+pub fn click_boosts(ctx: &ReducerContext, identity: Identity) -> (u128, u64) {
+    let mut multiplier = DECIMAL_SCALE_FACTOR;
+    let mut cooldown_reduction = 0u64;
+
+    for active in ctx
+        .db
+        .active_boost()
+        .identity()
+        .filter(identity)
+        .filter(|b| b.started_at <= ctx.timestamp && ctx.timestamp < b.expires_at)
+    {
+        match active.effect {
+            BoostEffect::ClickPowerMultiplier(boost) => {
+                multiplier += (boost as u128).saturating_sub(DECIMAL_SCALE_FACTOR)
+            }
+            BoostEffect::ClickCooldownReduction(reduction) => {
+                cooldown_reduction = cooldown_reduction.saturating_add(reduction)
+            }
+            BoostEffect::PassiveIncomeMultiplier(_) => {}
+        }
+    }
+
+    (multiplier, cooldown_reduction.min(BOOST_MAX_COOLDOWN_REDUCTION))
+}
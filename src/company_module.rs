@@ -0,0 +1,337 @@
+use crate::achievement_module::record_stats;
+use crate::constants::{
+    COMPANY_LISTING_FEE, COMPANY_MAX_FOUNDER_SHARE, COMPANY_MAX_IPO_PRICE, DECIMAL_SCALE_FACTOR,
+    IPO_DURATION_MICROS, PRICE_SCALE_FACTOR,
+};
+use crate::margin_module::margin_debt;
+use crate::order_book_module::trade;
+use crate::player_module::{player, settle_income, StockType};
+use crate::portfolio_module::revalue_player;
+use crate::price_model_module::bound_price;
+use crate::stock_module::{stock, MarketConfig, Stock};
+use spacetimedb::sats::u256;
+use spacetimedb::{
+    reducer, table, Identity, ReducerContext, SpacetimeType, Table, TimeDuration, Timestamp,
+};
+
+#[derive(SpacetimeType, PartialEq, Clone, Copy)]
+pub enum IpoStatus {
+    Subscribing,
+    Listed,
+}
+
+/// A player-founded company. Shares not kept by the founder are offered at `ipo_price`
+/// until `closes_at`; whatever is left unsubscribed goes to the house pool on listing.
+/// Founder shares stay locked in the IPO until another player has traded the stock.
+#[table(name = ipo, public)]
+pub struct Ipo {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    #[index(btree)]
+    pub founder: Identity,
+    pub name: String,
+    pub description: String,
+    pub total_shares: u64,
+    pub founder_shares: u64,
+    pub ipo_price: u128,
+    pub subscribed_shares: u64,
+    pub status: IpoStatus,
+    pub opened_at: Timestamp,
+    pub closes_at: Timestamp,
+    pub stock_id: Option<u16>,
+    pub founder_unlocked_at: Option<Timestamp>,
+}
+
+#[table(name = ipo_subscription, public)]
+pub struct IpoSubscription {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    #[index(btree)]
+    pub ipo_id: u64,
+    pub subscriber: Identity,
+    pub shares: u64,
+    pub paid: u128,
+    pub subscribed_at: Timestamp,
+}
+
+/// Money the house may still pay out for shares of a listed company. The IPO proceeds go to
+/// the founder, so it starts at zero, grows with every purchase from the pool and shrinks
+/// with every sale to it.
+#[table(name = ipo_buyback, public)]
+pub struct IpoBuyback {
+    #[primary_key]
+    pub stock_id: u16,
+    pub budget: u128,
+}
+
+/// Charges the listing fee and opens the IPO. `ipo_price` is unscaled, like the initial
+/// price passed to `create_stock`, and capped at `COMPANY_MAX_IPO_PRICE`.
+#[reducer]
+pub fn found_company(
+    ctx: &ReducerContext,
+    name: String,
+    description: String,
+    total_shares: u64,
+    founder_shares: u64,
+    ipo_price: u128,
+) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Company name cannot be empty.".to_string());
+    }
+
+    if total_shares == 0 {
+        return Err("Total shares cannot be zero.".to_string());
+    }
+
+    let max_founder_shares =
+        u128::from(total_shares) * u128::from(COMPANY_MAX_FOUNDER_SHARE) / DECIMAL_SCALE_FACTOR;
+    if u128::from(founder_shares) > max_founder_shares {
+        return Err("Founder allocation is too large.".to_string());
+    }
+
+    if ipo_price == 0 {
+        return Err("IPO price cannot be zero.".to_string());
+    }
+
+    if ipo_price > COMPANY_MAX_IPO_PRICE {
+        return Err("IPO price is too high.".to_string());
+    }
+    let ipo_price = ipo_price * PRICE_SCALE_FACTOR;
+
+    let name_taken = ctx.db.stock().iter().any(|s| s.name == name)
+        || ctx
+            .db
+            .ipo()
+            .iter()
+            .any(|i| i.status == IpoStatus::Subscribing && i.name == name);
+    if name_taken {
+        return Err("Company name is already taken.".to_string());
+    }
+
+    let Some(mut founder) = ctx.db.player().identity().find(ctx.sender) else {
+        return Err("Player not found.".to_string());
+    };
+
+    settle_income(ctx, &mut founder);
+    if founder.money < COMPANY_LISTING_FEE {
+        return Err("Not enough money for the listing fee.".to_string());
+    }
+
+    founder.money -= COMPANY_LISTING_FEE;
+    revalue_player(ctx, &mut founder);
+    ctx.db.player().identity().update(founder);
+
+    ctx.db.ipo().insert(Ipo {
+        id: 0,
+        founder: ctx.sender,
+        name,
+        description,
+        total_shares,
+        founder_shares,
+        ipo_price,
+        subscribed_shares: 0,
+        status: IpoStatus::Subscribing,
+        opened_at: ctx.timestamp,
+        closes_at: ctx.timestamp + TimeDuration::from_micros(IPO_DURATION_MICROS),
+        stock_id: None,
+        founder_unlocked_at: None,
+    });
+
+    Ok(())
+}
+
+/// Pays for `shares` at the IPO price up front. Shares are delivered when the company lists.
+#[reducer]
+pub fn subscribe_ipo(ctx: &ReducerContext, ipo_id: u64, shares: u64) -> Result<(), String> {
+    let Some(mut ipo) = ctx.db.ipo().id().find(ipo_id) else {
+        return Err("IPO not found.".to_string());
+    };
+
+    if ipo.status != IpoStatus::Subscribing || ctx.timestamp >= ipo.closes_at {
+        return Err("IPO is closed for subscriptions.".to_string());
+    }
+
+    if ipo.founder == ctx.sender {
+        return Err("Founders cannot subscribe to their own IPO.".to_string());
+    }
+
+    if shares == 0 {
+        return Err("Amount cannot be zero.".to_string());
+    }
+
+    if shares > ipo.total_shares - ipo.founder_shares - ipo.subscribed_shares {
+        return Err("Not enough IPO shares remaining.".to_string());
+    }
+
+    let Some(mut subscriber) = ctx.db.player().identity().find(ctx.sender) else {
+        return Err("Player not found.".to_string());
+    };
+
+    if margin_debt(ctx, ctx.sender) > 0 {
+        return Err("Repay your margin loan before subscribing to an IPO.".to_string());
+    }
+
+    let paid = ipo
+        .ipo_price
+        .checked_mul(shares.into())
+        .ok_or("Subscription is too large.".to_string())?;
+
+    settle_income(ctx, &mut subscriber);
+    if subscriber.money < u256::from(paid) {
+        return Err("Not enough money.".to_string());
+    }
+
+    subscriber.money -= u256::from(paid);
+    revalue_player(ctx, &mut subscriber);
+    ctx.db.player().identity().update(subscriber);
+
+    ipo.subscribed_shares += shares;
+    ctx.db.ipo().id().update(ipo);
+
+    ctx.db.ipo_subscription().insert(IpoSubscription {
+        id: 0,
+        ipo_id,
+        subscriber: ctx.sender,
+        shares,
+        paid,
+        subscribed_at: ctx.timestamp,
+    });
+
+    Ok(())
+}
+
+/// Lists every IPO that has closed or sold out as a tradable stock. The listing price is the
+/// IPO price scaled by the fraction of offered shares that was subscribed, so a thinly
+/// subscribed company lists cheaply. Subscribers receive their shares, the founder receives
+/// the raised money, and unsubscribed shares go to the house pool. The founder's allocation
+/// is held back until `unlock_founder_shares` sees a trade. Runs from `update_stock_prices`
+/// before the market settles, so new companies trade on the same tick.
+pub fn list_ipos(ctx: &ReducerContext, config: &MarketConfig) {
+    for mut ipo in ctx.db.ipo().iter().filter(|i| {
+        i.status == IpoStatus::Subscribing
+            && (ctx.timestamp >= i.closes_at
+                || i.founder_shares + i.subscribed_shares == i.total_shares)
+    }) {
+        let offered = ipo.total_shares - ipo.founder_shares;
+        let listing_price = u256::from(ipo.ipo_price) * u256::from(ipo.subscribed_shares)
+            / u256::from(offered.max(1));
+        let listing_price = bound_price(i128::try_from(listing_price).unwrap_or(i128::MAX), config);
+
+        let stock = ctx.db.stock().insert(Stock {
+            id: 0,
+            name: ipo.name.clone(),
+            description: ipo.description.clone(),
+            price_per_share: listing_price,
+            total_shares: ipo.total_shares,
+            available_shares: offered - ipo.subscribed_shares,
+            last_price: listing_price,
+            recent_buys: 0,
+            recent_sells: 0,
+        });
+
+        let mut raised = u256::new(0);
+        for subscription in ctx.db.ipo_subscription().ipo_id().filter(ipo.id) {
+            raised = raised.saturating_add(u256::from(subscription.paid));
+            if let Some(mut subscriber) = ctx.db.player().identity().find(subscription.subscriber) {
+                add_position(
+                    &mut subscriber.stocks,
+                    stock.id,
+                    subscription.shares,
+                    subscription.paid,
+                );
+                record_stats(ctx, &mut subscriber, |stats| stats.trades_executed += 1);
+                revalue_player(ctx, &mut subscriber);
+                ctx.db.player().identity().update(subscriber);
+            }
+        }
+
+        if let Some(mut founder) = ctx.db.player().identity().find(ipo.founder) {
+            founder.money = founder.money.saturating_add(raised);
+            revalue_player(ctx, &mut founder);
+            ctx.db.player().identity().update(founder);
+        }
+
+        ctx.db.ipo_buyback().insert(IpoBuyback {
+            stock_id: stock.id,
+            budget: 0,
+        });
+
+        log::info!("Listed {} after IPO {}", ipo.name, ipo.id);
+        ipo.status = IpoStatus::Listed;
+        ipo.stock_id = Some(stock.id);
+        ctx.db.ipo().id().update(ipo);
+    }
+}
+
+/// Delivers the founder's allocation once someone other than the founder has traded the
+/// listed stock. Until then the shares cannot be sold, transferred or counted in net worth.
+pub fn unlock_founder_shares(ctx: &ReducerContext) {
+    for mut ipo in ctx
+        .db
+        .ipo()
+        .iter()
+        .filter(|i| i.status == IpoStatus::Listed && i.founder_unlocked_at.is_none())
+    {
+        let Some(stock_id) = ipo.stock_id else {
+            continue;
+        };
+
+        let founder = ipo.founder;
+        let traded = ctx.db.trade().stock_id().filter(stock_id).any(|t| {
+            t.buyer.is_some_and(|b| b != founder) || t.seller.is_some_and(|s| s != founder)
+        });
+        if !traded {
+            continue;
+        }
+
+        if let Some(mut founder) = ctx.db.player().identity().find(founder) {
+            add_position(&mut founder.stocks, stock_id, ipo.founder_shares, 0);
+            revalue_player(ctx, &mut founder);
+            ctx.db.player().identity().update(founder);
+        }
+
+        ipo.founder_unlocked_at = Some(ctx.timestamp);
+        ctx.db.ipo().id().update(ipo);
+    }
+}
+
+/// Whether the house can pay `payout` for shares of `stock_id`. Stocks that did not come
+/// from an IPO have no buyback limit.
+pub fn ipo_buyback_available(ctx: &ReducerContext, stock_id: u16, payout: u128) -> bool {
+    ctx.db
+        .ipo_buyback()
+        .stock_id()
+        .find(stock_id)
+        .is_none_or(|b| b.budget >= payout)
+}
+
+/// Records money flowing into (`paid`) or out of (`payout`) the house for `stock_id`.
+pub fn record_ipo_flow(ctx: &ReducerContext, stock_id: u16, paid: u128, payout: u128) {
+    if let Some(mut buyback) = ctx.db.ipo_buyback().stock_id().find(stock_id) {
+        buyback.budget = buyback.budget.saturating_add(paid).saturating_sub(payout);
+        ctx.db.ipo_buyback().stock_id().update(buyback);
+    }
+}
+
+fn add_position(stocks: &mut Vec<StockType>, stock_id: u16, amount: u64, cost_basis: u128) {
+    if amount == 0 {
+        return;
+    }
+
+    match stocks.iter_mut().find(|s| s.stock_id == stock_id) {
+        Some(existing) => {
+            existing.amount += amount;
+            existing.cost_basis += cost_basis;
+        }
+        None => stocks.push(StockType {
+            stock_id,
+            amount,
+            cost_basis,
+            realized_pnl: 0,
+            unrealized_pnl: 0,
+        }),
+    }
+}
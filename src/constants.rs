@@ -1,13 +1,54 @@
 use spacetimedb::sats::u256;
 
-pub const PLAYER_UPDATE_INTERVAL_MICROS: u64 = 1_000_000;
+pub const PLAYER_INCOME_PERIOD_MICROS: i64 = 1_000_000;
 pub const PLAYER_STARTING_MONEY: u256 = u256::new(0);
 pub const PLAYER_STARTING_PASSIVE_INCOME: u128 = 0;
 pub const PLAYER_STARTING_CLICK_POWER: u128 = 1000;
 pub const PLAYER_STARTING_CLICK_TIMER_MICROS: i64 = 1_000_000;
 pub const PLAYER_STARTING_STOCK_BUY_FEE: u16 = 200;
 pub const PLAYER_STARTING_STOCK_SELL_FEE: u16 = 300;
+pub const PLAYER_STARTING_MAX_ORDER_SHARES: u64 = 1_000;
+pub const PLAYER_STARTING_COMBO_WINDOW_MICROS: i64 = 500_000;
+pub const PLAYER_STARTING_COMBO_STEP: u64 = 50;
+pub const PLAYER_STARTING_COMBO_MAX_MULTIPLIER: u64 = 2_000;
+pub const PLAYER_STARTING_CRIT_CHANCE: u64 = 20;
+pub const PLAYER_STARTING_CRIT_MULTIPLIER: u64 = 5_000;
+pub const PLAYER_MIN_CLICK_INTERVAL_MICROS: i64 = 50_000;
+pub const BOOST_MAX_COOLDOWN_REDUCTION: u64 = 750;
+pub const PLAYER_OFFLINE_EARNINGS_MULTIPLIER: u128 = 500;
+pub const PLAYER_OFFLINE_EARNINGS_CAP_MICROS: Option<i64> = Some(28_800_000_000);
 
 pub const STOCK_UPDATE_INTERVAL_MICROS: u64 = 1_000_000;
 pub const PRICE_SCALE_FACTOR: u128 = 1_000;
-pub const DECIMAL_SCALE_FACTOR: u128 = 1_000;
\ No newline at end of file
+pub const DECIMAL_SCALE_FACTOR: u128 = 1_000;
+pub const PRICE_HISTORY_MINUTE_MICROS: i64 = 60_000_000;
+pub const PRICE_HISTORY_HOUR_MICROS: i64 = 3_600_000_000;
+pub const PRICE_HISTORY_TICK_RETENTION_MICROS: i64 = 600_000_000;
+pub const PRICE_HISTORY_MINUTE_RETENTION_MICROS: i64 = 86_400_000_000;
+pub const PRICE_HISTORY_HOUR_RETENTION_MICROS: i64 = 2_592_000_000_000;
+
+pub const MARKET_EVENT_INTERVAL_MICROS: u64 = 300_000_000;
+pub const NEWS_RETENTION_MICROS: i64 = 86_400_000_000;
+
+pub const LEADERBOARD_UPDATE_INTERVAL_MICROS: u64 = 60_000_000;
+pub const LEADERBOARD_SIZE: usize = 100;
+pub const LEADERBOARD_DAY_MICROS: i64 = 86_400_000_000;
+pub const LEADERBOARD_WEEK_MICROS: i64 = 604_800_000_000;
+pub const LEADERBOARD_WEEK_OFFSET_MICROS: i64 = 345_600_000_000;
+
+pub const PRESTIGE_POINT_DIVISOR: u128 = 1_000_000_000;
+pub const PRESTIGE_BONUS_PER_POINT: u64 = 50;
+
+pub const ORDER_BOOK_PRICE_BAND: u128 = 100;
+
+pub const TRANSFER_LIMIT_WINDOW_MICROS: i64 = 86_400_000_000;
+
+pub const SHORT_BORROW_FEE_PERIOD_MICROS: u64 = 3_600_000_000;
+pub const MARGIN_INTEREST_PERIOD_MICROS: u64 = 3_600_000_000;
+
+pub const DIVIDEND_CHECK_INTERVAL_MICROS: u64 = 60_000_000;
+
+pub const COMPANY_LISTING_FEE: u256 = u256::new(100_000_000);
+pub const COMPANY_MAX_FOUNDER_SHARE: u64 = 500;
+pub const COMPANY_MAX_IPO_PRICE: u128 = 100;
+pub const IPO_DURATION_MICROS: i64 = 600_000_000;
@@ -0,0 +1,225 @@
+use crate::achievement_module::record_stats;
+use crate::admin_module::ensure_admin;
+use crate::constants::{DECIMAL_SCALE_FACTOR, DIVIDEND_CHECK_INTERVAL_MICROS};
+use crate::player_module::player;
+use crate::portfolio_module::{holding, revalue_player};
+use crate::stock_module::stock;
+use spacetimedb::sats::u256;
+use spacetimedb::{
+    reducer, table, Identity, ReducerContext, ScheduleAt, Table, TimeDuration, Timestamp,
+};
+use std::time::Duration;
+
+/// `yield_rate` is the fraction of the share price paid per share on each payout, scaled by
+/// `DECIMAL_SCALE_FACTOR`.
+#[table(name = dividend_policy, public)]
+pub struct DividendPolicy {
+    #[primary_key]
+    pub stock_id: u16,
+    pub yield_rate: u64,
+    pub payout_interval_micros: i64,
+    pub next_payout_at: Timestamp,
+}
+
+#[table(name = dividend_preference, public)]
+pub struct DividendPreference {
+    #[primary_key]
+    pub identity: Identity,
+    pub reinvest: bool,
+}
+
+#[table(name = dividend_payout, public)]
+pub struct DividendPayout {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    #[index(btree)]
+    pub stock_id: u16,
+    #[index(btree)]
+    pub identity: Identity,
+    pub shares_held: u64,
+    pub amount: u128,
+    pub reinvested_shares: u64,
+    pub paid_at: Timestamp,
+}
+
+#[table(name = dividend_schedule, scheduled(pay_dividends))]
+pub struct DividendSchedule {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub scheduled_at: ScheduleAt,
+}
+
+#[reducer]
+pub fn set_dividend_policy(
+    ctx: &ReducerContext,
+    stock_id: u16,
+    yield_rate: u64,
+    payout_interval_micros: i64,
+) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    if ctx.db.stock().id().find(stock_id).is_none() {
+        return Err("Stock not found.".to_string());
+    }
+
+    if payout_interval_micros <= 0 {
+        return Err("Payout interval must be positive.".to_string());
+    }
+
+    let policy = DividendPolicy {
+        stock_id,
+        yield_rate,
+        payout_interval_micros,
+        next_payout_at: ctx.timestamp + TimeDuration::from_micros(payout_interval_micros),
+    };
+
+    if ctx.db.dividend_policy().stock_id().find(stock_id).is_some() {
+        ctx.db.dividend_policy().stock_id().update(policy);
+    } else {
+        ctx.db.dividend_policy().insert(policy);
+    }
+
+    Ok(())
+}
+
+#[reducer]
+pub fn remove_dividend_policy(ctx: &ReducerContext, stock_id: u16) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    if !ctx.db.dividend_policy().stock_id().delete(stock_id) {
+        return Err("Dividend policy not found.".to_string());
+    }
+
+    Ok(())
+}
+
+/// When enabled, each dividend buys as many whole shares of the paying stock as it covers at
+/// the current price, fee-free from the pool. The remainder is paid out as cash.
+#[reducer]
+pub fn set_dividend_reinvestment(ctx: &ReducerContext, reinvest: bool) -> Result<(), String> {
+    if ctx.db.player().identity().find(ctx.sender).is_none() {
+        return Err("Player not found.".to_string());
+    }
+
+    let preference = DividendPreference {
+        identity: ctx.sender,
+        reinvest,
+    };
+
+    if ctx
+        .db
+        .dividend_preference()
+        .identity()
+        .find(ctx.sender)
+        .is_some()
+    {
+        ctx.db.dividend_preference().identity().update(preference);
+    } else {
+        ctx.db.dividend_preference().insert(preference);
+    }
+
+    Ok(())
+}
+
+/// Pays every policy that has come due, crediting each holder in proportion to their share
+/// count and recording one history row per holder. Holders are found through the `holding`
+/// index, and the payout is credited directly so passive income keeps its own settlement.
+#[reducer]
+pub fn pay_dividends(ctx: &ReducerContext, _args: DividendSchedule) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    for mut policy in ctx
+        .db
+        .dividend_policy()
+        .iter()
+        .filter(|p| p.next_payout_at <= ctx.timestamp)
+    {
+        policy.next_payout_at =
+            ctx.timestamp + TimeDuration::from_micros(policy.payout_interval_micros);
+        let stock_id = policy.stock_id;
+        let yield_rate = policy.yield_rate;
+        ctx.db.dividend_policy().stock_id().update(policy);
+
+        let Some(mut stock) = ctx.db.stock().id().find(stock_id) else {
+            continue;
+        };
+
+        let per_share = u256::from(stock.price_per_share) * u256::from(yield_rate)
+            / u256::from(DECIMAL_SCALE_FACTOR);
+        if per_share == u256::new(0) {
+            continue;
+        }
+
+        let holders: Vec<Identity> = ctx
+            .db
+            .holding()
+            .stock_id()
+            .filter(stock_id)
+            .map(|h| h.identity)
+            .collect();
+        for identity in holders {
+            let Some(mut holder) = ctx.db.player().identity().find(identity) else {
+                continue;
+            };
+            let Some(shares_held) = holder
+                .stocks
+                .iter()
+                .find(|s| s.stock_id == stock_id)
+                .map(|s| s.amount)
+            else {
+                continue;
+            };
+            let amount = u128::try_from(per_share * u256::from(shares_held)).unwrap_or(u128::MAX);
+
+            let reinvest = ctx
+                .db
+                .dividend_preference()
+                .identity()
+                .find(holder.identity)
+                .is_some_and(|p| p.reinvest);
+            let reinvested_shares = if reinvest {
+                u64::try_from(amount / stock.price_per_share.max(1))
+                    .unwrap_or(u64::MAX)
+                    .min(stock.available_shares)
+            } else {
+                0
+            };
+            let reinvested_cost = stock.price_per_share * reinvested_shares as u128;
+
+            if let Some(position) = holder.stocks.iter_mut().find(|s| s.stock_id == stock_id) {
+                position.amount += reinvested_shares;
+                position.cost_basis += reinvested_cost;
+            }
+            stock.available_shares -= reinvested_shares;
+            holder.money += u256::from(amount - reinvested_cost);
+            record_stats(ctx, &mut holder, |stats| {
+                stats.lifetime_earnings += u256::from(amount)
+            });
+
+            ctx.db.dividend_payout().insert(DividendPayout {
+                id: 0,
+                stock_id,
+                identity: holder.identity,
+                shares_held,
+                amount,
+                reinvested_shares,
+                paid_at: ctx.timestamp,
+            });
+
+            revalue_player(ctx, &mut holder);
+            ctx.db.player().identity().update(holder);
+        }
+
+        ctx.db.stock().id().update(stock);
+    }
+
+    ctx.db.dividend_schedule().insert(DividendSchedule {
+        id: 0,
+        scheduled_at: (ctx.timestamp + Duration::from_micros(DIVIDEND_CHECK_INTERVAL_MICROS))
+            .into(),
+    });
+
+    Ok(())
+}
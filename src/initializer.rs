@@ -1,117 +1,247 @@
-use crate::stock_module::{create_stock, init_market_config};
-use crate::upgrades_module::add_upgrade;
-use spacetimedb::ReducerContext;
+use crate::achievement_module::{add_achievement, AchievementCriterion};
+use crate::admin_module::init_admin;
+use crate::anti_cheat_module::{init_anti_cheat_config, AntiCheatAction};
+use crate::boost_module::{add_boost, BoostEffect};
+use crate::dividend_module::set_dividend_policy;
+use crate::margin_module::init_margin_config;
+use crate::market_event_module::add_market_event;
+use crate::price_model_module::{set_price_model, PriceModelKind};
+use crate::short_module::init_short_config;
+use crate::stock_module::{create_stock, init_market_config, stock, Stock};
+use crate::transfer_module::init_transfer_config;
+use crate::transaction_module::OrderType;
+use crate::upgrades_module::{
+    add_upgrade, set_upgrade_order_unlocks, set_upgrade_prerequisites, CostCurve, ModifierKind,
+    PlayerStat, StackingRule, UpgradeEffect,
+};
+use spacetimedb::{ReducerContext, Table};
 
 pub fn initializer(ctx: &ReducerContext) {
-    init_market_config(ctx, 20, 10, 1).expect("Market failed to init configuration");
+    init_admin(ctx).expect("Failed to seed admin");
+    init_market_config(ctx, 20, 10, 1, 1_000_000_000_000).expect("Market failed to init configuration");
+    init_anti_cheat_config(
+        ctx,
+        30,
+        5_000,
+        1_800_000_000,
+        950,
+        AntiCheatAction::SoftPenalty,
+        250,
+        600_000_000,
+        3_600_000_000,
+    )
+    .expect("Failed to init anti-cheat configuration");
+    init_transfer_config(ctx, 10, 20, None, Some(100_000))
+        .expect("Failed to init transfer configuration");
+    init_short_config(ctx, 10, 1_500, 1_250).expect("Failed to init short selling configuration");
+    init_margin_config(ctx, 3_000, 5, 250).expect("Failed to init margin configuration");
     init_upgrades(ctx);
     init_stocks(ctx);
+    init_price_models(ctx);
+    init_market_events(ctx);
+    init_dividends(ctx);
+    init_achievements(ctx);
+    init_boosts(ctx);
 }
 
 pub fn init_upgrades(ctx: &ReducerContext) {
     add_upgrade(
         ctx,
-        "passive_income_lv1".into(),
-        "Passive Income I".into(),
-        "Gain +0.2 passive income/sec.".into(),
+        "passive_income".into(),
+        "Passive Income".into(),
+        "Gain +0.2 passive income/sec per level.".into(),
         1,
         10_000,
-        Some(200),
-        None,
-        None,
+        vec![additive(PlayerStat::PassiveIncome, 200)],
+        10,
+        CostCurve::Exponential(1_500),
     )
     .expect("Failed to add upgrade");
 
     add_upgrade(
         ctx,
-        "passive_income_lv2".into(),
-        "Passive Income II".into(),
-        "Gain +1.0 passive income/sec.".into(),
+        "compound_interest".into(),
+        "Compound Interest".into(),
+        "Increase passive income by 10% per level.".into(),
         2,
-        50_000,
-        Some(1_000),
-        None,
-        None,
+        150_000,
+        vec![multiplier(PlayerStat::PassiveIncome, 100)],
+        5,
+        CostCurve::Exponential(2_000),
+    )
+    .expect("Failed to add upgrade");
+
+    add_upgrade(
+        ctx,
+        "click_power".into(),
+        "Click Power".into(),
+        "Increase click power by 2 per level.".into(),
+        1,
+        20_000,
+        vec![additive(PlayerStat::ClickPower, 2)],
+        10,
+        CostCurve::Exponential(1_600),
     )
     .expect("Failed to add upgrade");
 
     add_upgrade(
         ctx,
-        "passive_income_lv3".into(),
-        "Passive Income III".into(),
-        "Gain +5.0 passive income/sec.".into(),
+        "golden_mouse".into(),
+        "Golden Mouse".into(),
+        "Double your click power.".into(),
         3,
-        300_000,
-        Some(5_000),
-        None,
-        None,
+        500_000,
+        vec![UpgradeEffect {
+            stat: PlayerStat::ClickPower,
+            kind: ModifierKind::Multiplicative,
+            value: 1_000,
+            stacking: StackingRule::Once,
+        }],
+        1,
+        CostCurve::Custom(vec![500_000]),
     )
     .expect("Failed to add upgrade");
 
     add_upgrade(
         ctx,
-        "click_power_lv1".into(),
-        "Click Power I".into(),
-        "Increase click power by 2.".into(),
+        "faster_clicks".into(),
+        "Faster Clicks".into(),
+        "Reduce click timer by 100 ms per level.".into(),
         1,
-        20_000,
-        None,
-        Some(2),
-        None,
+        30_000,
+        vec![additive(PlayerStat::ClickTimer, -100_000)],
+        4,
+        CostCurve::Linear(30_000),
     )
     .expect("Failed to add upgrade");
 
     add_upgrade(
         ctx,
-        "click_power_lv2".into(),
-        "Click Power II".into(),
-        "Increase click power by 5.".into(),
+        "turbo_clicks".into(),
+        "Turbo Clicks".into(),
+        "Reduce click timer by 500 ms.".into(),
         2,
-        100_000,
-        None,
-        Some(5),
-        None,
+        200_000,
+        vec![additive(PlayerStat::ClickTimer, -500_000)],
+        1,
+        CostCurve::Custom(vec![200_000]),
     )
     .expect("Failed to add upgrade");
 
     add_upgrade(
         ctx,
-        "click_power_lv3".into(),
-        "Click Power III".into(),
-        "Increase click power by 10.".into(),
-        3,
-        500_000,
-        None,
-        Some(10),
-        None,
+        "combo_training".into(),
+        "Combo Training".into(),
+        "Widen the combo window by 100 ms and add +5% per chained click per level.".into(),
+        1,
+        35_000,
+        vec![
+            additive(PlayerStat::ComboWindow, 100_000),
+            additive(PlayerStat::ComboStep, 50),
+        ],
+        5,
+        CostCurve::Exponential(1_800),
     )
     .expect("Failed to add upgrade");
 
     add_upgrade(
         ctx,
-        "faster_clicks".into(),
-        "Faster Clicks".into(),
-        "Reduce click timer by 100 ms.".into(),
+        "lucky_fingers".into(),
+        "Lucky Fingers".into(),
+        "Increase critical click chance by 1% per level.".into(),
         1,
-        30_000,
-        None,
-        None,
-        Some(100_000),
+        50_000,
+        vec![additive(PlayerStat::CritChance, 10)],
+        10,
+        CostCurve::Exponential(1_500),
     )
     .expect("Failed to add upgrade");
 
     add_upgrade(
         ctx,
-        "turbo_clicks".into(),
-        "Turbo Clicks".into(),
-        "Reduce click timer by 500 ms.".into(),
+        "critical_mass".into(),
+        "Critical Mass".into(),
+        "Critical clicks pay out 1x more per level.".into(),
+        2,
+        120_000,
+        vec![additive(PlayerStat::CritMultiplier, 1_000)],
+        5,
+        CostCurve::Exponential(2_000),
+    )
+    .expect("Failed to add upgrade");
+
+    add_upgrade(
+        ctx,
+        "broker_discount".into(),
+        "Broker Discount".into(),
+        "Reduce buy fees by 2% and sell fees by 3% per level.".into(),
         1,
-        200_000,
-        None,
-        None,
-        Some(500_000),
+        40_000,
+        vec![
+            additive(PlayerStat::StockBuyFee, -20),
+            additive(PlayerStat::StockSellFee, -30),
+        ],
+        5,
+        CostCurve::Exponential(2_000),
+    )
+    .expect("Failed to add upgrade");
+
+    add_upgrade(
+        ctx,
+        "bulk_trading".into(),
+        "Bulk Trading".into(),
+        "Raise the per-order share limit by 1000 per level.".into(),
+        1,
+        15_000,
+        vec![additive(PlayerStat::MaxOrderShares, 1_000)],
+        10,
+        CostCurve::Exponential(1_500),
     )
     .expect("Failed to add upgrade");
+
+    add_upgrade(
+        ctx,
+        "short_selling".into(),
+        "Short Selling".into(),
+        "Unlock short positions.".into(),
+        2,
+        100_000,
+        Vec::new(),
+        1,
+        CostCurve::Custom(vec![100_000]),
+    )
+    .expect("Failed to add upgrade");
+
+    add_upgrade(
+        ctx,
+        "margin_trading".into(),
+        "Margin Trading".into(),
+        "Unlock margin accounts.".into(),
+        2,
+        150_000,
+        Vec::new(),
+        1,
+        CostCurve::Custom(vec![150_000]),
+    )
+    .expect("Failed to add upgrade");
+
+    set_upgrade_order_unlocks(ctx, "short_selling".into(), vec![OrderType::ShortSell])
+        .expect("Failed to set upgrade order unlocks");
+    set_upgrade_order_unlocks(ctx, "margin_trading".into(), vec![OrderType::Margin])
+        .expect("Failed to set upgrade order unlocks");
+
+    set_upgrade_prerequisites(ctx, "compound_interest".into(), vec!["passive_income".into()], 0, 0)
+        .expect("Failed to set upgrade prerequisites");
+    set_upgrade_prerequisites(ctx, "golden_mouse".into(), vec!["click_power".into()], 0, 5_000)
+        .expect("Failed to set upgrade prerequisites");
+    set_upgrade_prerequisites(ctx, "critical_mass".into(), vec!["lucky_fingers".into()], 0, 0)
+        .expect("Failed to set upgrade prerequisites");
+    set_upgrade_prerequisites(ctx, "click_power".into(), Vec::new(), 0, 100)
+        .expect("Failed to set upgrade prerequisites");
+    set_upgrade_prerequisites(ctx, "faster_clicks".into(), vec!["click_power".into()], 0, 500)
+        .expect("Failed to set upgrade prerequisites");
+    set_upgrade_prerequisites(ctx, "turbo_clicks".into(), vec!["faster_clicks".into()], 150_000, 2_000)
+        .expect("Failed to set upgrade prerequisites");
 }
 
 pub fn init_stocks(ctx: &ReducerContext) {
@@ -169,3 +299,160 @@ pub fn init_stocks(ctx: &ReducerContext) {
     )
     .expect("Failed to create GenAILabs");
 }
+
+pub fn init_price_models(ctx: &ReducerContext) {
+    let models = [
+        ("QuantumCompute", PriceModelKind::Hybrid, 0, 3, 10),
+        ("EtherFiber", PriceModelKind::RandomWalk, 0, 4, 0),
+        ("MarsVacations", PriceModelKind::RandomWalk, 0, 6, 0),
+        ("CaffeineInc", PriceModelKind::MeanReversion, 0, 2, 20),
+        ("RoboFarm", PriceModelKind::Hybrid, 0, 2, 5),
+        ("GenAILabs", PriceModelKind::Demand, 0, 0, 0),
+    ];
+
+    for (name, kind, drift, volatility, reversion_speed) in models {
+        let stock = find_stock(ctx, name);
+
+        set_price_model(
+            ctx,
+            stock.id,
+            kind,
+            drift,
+            volatility,
+            stock.price_per_share,
+            reversion_speed,
+        )
+        .expect("Failed to set price model");
+    }
+}
+
+pub fn init_dividends(ctx: &ReducerContext) {
+    let policies = [
+        ("CaffeineInc", 5, 3_600_000_000),
+        ("RoboFarm", 3, 3_600_000_000),
+        ("EtherFiber", 20, 86_400_000_000),
+    ];
+
+    for (name, yield_rate, payout_interval_micros) in policies {
+        let stock = find_stock(ctx, name);
+
+        set_dividend_policy(ctx, stock.id, yield_rate, payout_interval_micros)
+            .expect("Failed to set dividend policy");
+    }
+}
+
+pub fn init_market_events(ctx: &ReducerContext) {
+    let events = [
+        ("RoboFarm", "RoboFarm crop yields double", 80, 1, 60_000_000, 10),
+        ("RoboFarm", "Locust swarm outsmarts RoboFarm drones", -100, -1, 60_000_000, 5),
+        ("QuantumCompute", "QuantumCompute achieves stable 1M-qubit chip", 150, 2, 30_000_000, 5),
+        ("QuantumCompute", "QuantumCompute chip found to be a very fast toaster", -200, -1, 60_000_000, 3),
+        ("MarsVacations", "Dust storm grounds all MarsVacations flights", -120, -1, 120_000_000, 6),
+        ("MarsVacations", "MarsVacations opens Olympus Mons ski resort", 100, 1, 60_000_000, 6),
+        ("CaffeineInc", "Study links CaffeineInc crystals to 40-hour workdays", 60, 0, 0, 8),
+        ("EtherFiber", "EtherFiber routes traffic through a llama farm", -80, 0, 0, 8),
+        ("GenAILabs", "GenAILabs model writes its own press release", 120, 1, 30_000_000, 6),
+    ];
+
+    for (name, headline, price_shock, drift, duration_micros, weight) in events {
+        let stock = find_stock(ctx, name);
+
+        add_market_event(
+            ctx,
+            stock.id,
+            headline.into(),
+            price_shock,
+            drift,
+            duration_micros,
+            weight,
+        )
+        .expect("Failed to add market event");
+    }
+}
+
+pub fn init_achievements(ctx: &ReducerContext) {
+    let achievements = [
+        ("first_click", "First Click", "Click for the first time.", AchievementCriterion::TotalClicks(1), 0),
+        ("clicker", "Clicker", "Click 1,000 times.", AchievementCriterion::TotalClicks(1_000), 5_000),
+        ("click_machine", "Click Machine", "Click 100,000 times.", AchievementCriterion::TotalClicks(100_000), 250_000),
+        ("first_fortune", "First Fortune", "Earn 100,000 in total.", AchievementCriterion::LifetimeEarnings(100_000), 10_000),
+        ("tycoon", "Tycoon", "Earn 10,000,000 in total.", AchievementCriterion::LifetimeEarnings(10_000_000), 500_000),
+        ("first_trade", "First Trade", "Execute your first trade.", AchievementCriterion::TradesExecuted(1), 1_000),
+        ("day_trader", "Day Trader", "Execute 100 trades.", AchievementCriterion::TradesExecuted(100), 50_000),
+        ("big_win", "Big Win", "Make a profit of 50,000 on a single trade.", AchievementCriterion::BiggestSingleProfit(50_000), 25_000),
+        ("shopper", "Shopper", "Buy 10 upgrades.", AchievementCriterion::UpgradesBought(10), 20_000),
+    ];
+
+    for (identifier, title, description, criterion, reward_money) in achievements {
+        add_achievement(
+            ctx,
+            identifier.into(),
+            title.into(),
+            description.into(),
+            criterion,
+            reward_money,
+        )
+        .expect("Failed to add achievement");
+    }
+}
+
+pub fn init_boosts(ctx: &ReducerContext) {
+    add_boost(
+        ctx,
+        "coffee_break".into(),
+        "Coffee Break".into(),
+        "2x passive income for 10 minutes.".into(),
+        25_000,
+        600_000_000,
+        BoostEffect::PassiveIncomeMultiplier(2_000),
+    )
+    .expect("Failed to add boost");
+
+    add_boost(
+        ctx,
+        "adrenaline_rush".into(),
+        "Adrenaline Rush".into(),
+        "Halves click cooldown for 30 seconds.".into(),
+        15_000,
+        30_000_000,
+        BoostEffect::ClickCooldownReduction(500),
+    )
+    .expect("Failed to add boost");
+
+    add_boost(
+        ctx,
+        "golden_hour".into(),
+        "Golden Hour".into(),
+        "3x click power for 60 seconds.".into(),
+        20_000,
+        60_000_000,
+        BoostEffect::ClickPowerMultiplier(3_000),
+    )
+    .expect("Failed to add boost");
+}
+
+fn find_stock(ctx: &ReducerContext, name: &str) -> Stock {
+    ctx.db
+        .stock()
+        .iter()
+        .find(|s| s.name == name)
+        .expect("Failed to find stock")
+}
+
+fn additive(stat: PlayerStat, value: i64) -> UpgradeEffect {
+    UpgradeEffect {
+        stat,
+        kind: ModifierKind::Additive,
+        value,
+        stacking: StackingRule::PerLevel,
+    }
+}
+
+fn multiplier(stat: PlayerStat, value: i64) -> UpgradeEffect {
+    UpgradeEffect {
+        stat,
+        kind: ModifierKind::Multiplicative,
+        value,
+        stacking: StackingRule::PerLevel,
+    }
+}
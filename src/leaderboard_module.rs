@@ -0,0 +1,161 @@
+use crate::achievement_module::player_stats;
+use crate::admin_module::ensure_admin;
+use crate::constants::{
+    LEADERBOARD_DAY_MICROS, LEADERBOARD_SIZE, LEADERBOARD_UPDATE_INTERVAL_MICROS,
+    LEADERBOARD_WEEK_MICROS, LEADERBOARD_WEEK_OFFSET_MICROS,
+};
+use crate::player_module::{player, Player};
+use spacetimedb::{reducer, table, Identity, ReducerContext, ScheduleAt, SpacetimeType, Table, Timestamp};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(SpacetimeType, PartialEq, Clone, Copy)]
+pub enum LeaderboardCategory {
+    NetWorth,
+    TotalClicks,
+    TradingProfit,
+}
+
+#[derive(SpacetimeType, PartialEq, Clone, Copy)]
+pub enum SnapshotPeriod {
+    Daily,
+    Weekly,
+}
+
+/// Scores are saturated into `i128` so every category shares one column.
+#[table(name = leaderboard, public)]
+pub struct LeaderboardEntry {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub category: LeaderboardCategory,
+    pub rank: u32,
+    pub identity: Identity,
+    pub username: Option<String>,
+    pub score: i128,
+}
+
+#[table(name = leaderboard_snapshot, public)]
+pub struct LeaderboardSnapshot {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub period: SnapshotPeriod,
+    #[index(btree)]
+    pub period_start_micros: i64,
+    pub category: LeaderboardCategory,
+    pub rank: u32,
+    pub identity: Identity,
+    pub username: Option<String>,
+    pub score: i128,
+    pub taken_at: Timestamp,
+}
+
+#[table(name = leaderboard_schedule, scheduled(update_leaderboard))]
+pub struct LeaderboardSchedule {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub scheduled_at: ScheduleAt,
+}
+
+const CATEGORIES: [LeaderboardCategory; 3] = [
+    LeaderboardCategory::NetWorth,
+    LeaderboardCategory::TotalClicks,
+    LeaderboardCategory::TradingProfit,
+];
+
+#[reducer]
+pub fn update_leaderboard(ctx: &ReducerContext, _args: LeaderboardSchedule) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    let stale: Vec<u64> = ctx.db.leaderboard().iter().map(|e| e.id).collect();
+    for id in stale {
+        ctx.db.leaderboard().id().delete(id);
+    }
+
+    let players: Vec<Player> = ctx.db.player().iter().collect();
+    let clicks: HashMap<Identity, u64> = ctx
+        .db
+        .player_stats()
+        .iter()
+        .map(|s| (s.identity, s.total_clicks))
+        .collect();
+    for category in CATEGORIES {
+        for (rank, (player, score)) in rank_players(&players, &clicks, category).into_iter().enumerate() {
+            ctx.db.leaderboard().insert(LeaderboardEntry {
+                id: 0,
+                category,
+                rank: rank as u32 + 1,
+                identity: player.identity,
+                username: player.username.clone(),
+                score,
+            });
+        }
+    }
+
+    let now = ctx.timestamp.to_micros_since_unix_epoch();
+    take_snapshot(ctx, SnapshotPeriod::Daily, now - now.rem_euclid(LEADERBOARD_DAY_MICROS));
+    take_snapshot(
+        ctx,
+        SnapshotPeriod::Weekly,
+        now - (now - LEADERBOARD_WEEK_OFFSET_MICROS).rem_euclid(LEADERBOARD_WEEK_MICROS),
+    );
+
+    ctx.db.leaderboard_schedule().insert(LeaderboardSchedule {
+        id: 0,
+        scheduled_at: (ctx.timestamp + Duration::from_micros(LEADERBOARD_UPDATE_INTERVAL_MICROS)).into(),
+    });
+
+    Ok(())
+}
+
+fn rank_players<'a>(
+    players: &'a [Player],
+    clicks: &HashMap<Identity, u64>,
+    category: LeaderboardCategory,
+) -> Vec<(&'a Player, i128)> {
+    let mut ranked: Vec<(&Player, i128)> = players
+        .iter()
+        .map(|p| (p, score(p, clicks, category)))
+        .collect();
+    ranked.sort_by_key(|(_, score)| Reverse(*score));
+    ranked.truncate(LEADERBOARD_SIZE);
+    ranked
+}
+
+fn score(player: &Player, clicks: &HashMap<Identity, u64>, category: LeaderboardCategory) -> i128 {
+    match category {
+        LeaderboardCategory::NetWorth => i128::try_from(player.net_worth).unwrap_or(i128::MAX),
+        LeaderboardCategory::TotalClicks => clicks.get(&player.identity).copied().unwrap_or(0) as i128,
+        LeaderboardCategory::TradingProfit => player.realized_pnl,
+    }
+}
+
+/// Copies the live leaderboard into history the first time it is built in a new period.
+fn take_snapshot(ctx: &ReducerContext, period: SnapshotPeriod, period_start_micros: i64) {
+    let exists = ctx
+        .db
+        .leaderboard_snapshot()
+        .period_start_micros()
+        .filter(period_start_micros)
+        .any(|s| s.period == period);
+    if exists {
+        return;
+    }
+
+    for entry in ctx.db.leaderboard().iter() {
+        ctx.db.leaderboard_snapshot().insert(LeaderboardSnapshot {
+            id: 0,
+            period,
+            period_start_micros,
+            category: entry.category,
+            rank: entry.rank,
+            identity: entry.identity,
+            username: entry.username,
+            score: entry.score,
+            taken_at: ctx.timestamp,
+        });
+    }
+}
@@ -1,18 +1,43 @@
+mod achievement_module;
+mod admin_module;
+mod anti_cheat_module;
+mod boost_module;
+mod company_module;
 mod constants;
+mod dividend_module;
+mod order_book_module;
 mod player_module;
+mod portfolio_module;
+mod prestige_module;
+mod short_module;
+mod price_history_module;
+mod price_model_module;
 mod stock_module;
 mod transaction_module;
+mod transfer_module;
 mod upgrades_module;
 mod initializer;
+mod leaderboard_module;
+mod margin_module;
+mod market_event_module;
 
 use crate::constants::{
-    PLAYER_STARTING_CLICK_POWER, PLAYER_STARTING_CLICK_TIMER_MICROS, PLAYER_STARTING_MONEY,
-    PLAYER_STARTING_PASSIVE_INCOME, PLAYER_STARTING_STOCK_BUY_FEE, PLAYER_STARTING_STOCK_SELL_FEE,
-    PLAYER_UPDATE_INTERVAL_MICROS, STOCK_UPDATE_INTERVAL_MICROS,
+    DECIMAL_SCALE_FACTOR, DIVIDEND_CHECK_INTERVAL_MICROS, LEADERBOARD_UPDATE_INTERVAL_MICROS, MARKET_EVENT_INTERVAL_MICROS,
+    PLAYER_STARTING_CLICK_POWER, PLAYER_STARTING_CLICK_TIMER_MICROS,
+    PLAYER_STARTING_COMBO_MAX_MULTIPLIER, PLAYER_STARTING_COMBO_STEP,
+    PLAYER_STARTING_COMBO_WINDOW_MICROS, PLAYER_STARTING_CRIT_CHANCE, PLAYER_STARTING_CRIT_MULTIPLIER,
+    PLAYER_STARTING_MAX_ORDER_SHARES, PLAYER_STARTING_MONEY, PLAYER_STARTING_PASSIVE_INCOME,
+    PLAYER_STARTING_STOCK_BUY_FEE, PLAYER_STARTING_STOCK_SELL_FEE, STOCK_UPDATE_INTERVAL_MICROS,
 };
+use crate::dividend_module::{dividend_schedule, DividendSchedule};
 use crate::initializer::initializer;
-use crate::player_module::{player, update_player_schedule, Player, UpdatePlayersSchedule};
+use crate::leaderboard_module::{leaderboard_schedule, LeaderboardSchedule};
+use crate::market_event_module::{market_event_schedule, MarketEventSchedule};
+use crate::player_module::{player, settle_income, Player};
+use crate::portfolio_module::revalue_player;
 use crate::stock_module::{stock_market_schedule, StockMarketSchedule};
+use crate::transaction_module::DEFAULT_ORDER_TYPES;
+use crate::upgrades_module::refresh_unlockable_upgrades;
 use spacetimedb::{reducer, ReducerContext, Table};
 use std::time::Duration;
 
@@ -20,16 +45,22 @@ use std::time::Duration;
 pub fn init(ctx: &ReducerContext) {
     initializer(ctx);
 
-    ctx.db
-        .update_player_schedule()
-        .insert(UpdatePlayersSchedule {
-            id: 0,
-            scheduled_at: (ctx.timestamp + Duration::from_micros(PLAYER_UPDATE_INTERVAL_MICROS)).into(),
-        });
     ctx.db.stock_market_schedule().insert(StockMarketSchedule {
         id: 0,
         scheduled_at: (ctx.timestamp + Duration::from_micros(STOCK_UPDATE_INTERVAL_MICROS)).into(),
     });
+    ctx.db.market_event_schedule().insert(MarketEventSchedule {
+        id: 0,
+        scheduled_at: (ctx.timestamp + Duration::from_micros(MARKET_EVENT_INTERVAL_MICROS)).into(),
+    });
+    ctx.db.leaderboard_schedule().insert(LeaderboardSchedule {
+        id: 0,
+        scheduled_at: (ctx.timestamp + Duration::from_micros(LEADERBOARD_UPDATE_INTERVAL_MICROS)).into(),
+    });
+    ctx.db.dividend_schedule().insert(DividendSchedule {
+        id: 0,
+        scheduled_at: (ctx.timestamp + Duration::from_micros(DIVIDEND_CHECK_INTERVAL_MICROS)).into(),
+    });
 }
 
 #[reducer(client_connected)]
@@ -37,22 +68,43 @@ pub fn identity_connected(ctx: &ReducerContext) {
     let identity = ctx.sender;
 
     if ctx.db.player().identity().find(identity).is_none() {
-        ctx.db.player().insert(Player {
+        let mut player = Player {
             identity,
             username: None,
             money: PLAYER_STARTING_MONEY,
+            net_worth: PLAYER_STARTING_MONEY,
+            realized_pnl: 0,
+            last_income_at: ctx.timestamp,
+            offline_since: ctx.timestamp,
             passive_income: PLAYER_STARTING_PASSIVE_INCOME,
             click_power: PLAYER_STARTING_CLICK_POWER,
             click_timer: PLAYER_STARTING_CLICK_TIMER_MICROS,
             stock_buy_fee: PLAYER_STARTING_STOCK_BUY_FEE,
             stock_sell_fee: PLAYER_STARTING_STOCK_SELL_FEE,
+            max_order_shares: PLAYER_STARTING_MAX_ORDER_SHARES,
+            unlocked_order_types: DEFAULT_ORDER_TYPES.to_vec(),
             last_click: ctx.timestamp,
+            combo_window: PLAYER_STARTING_COMBO_WINDOW_MICROS,
+            combo_step: PLAYER_STARTING_COMBO_STEP,
+            combo_max_multiplier: PLAYER_STARTING_COMBO_MAX_MULTIPLIER,
+            crit_chance: PLAYER_STARTING_CRIT_CHANCE,
+            crit_multiplier: PLAYER_STARTING_CRIT_MULTIPLIER,
+            combo_count: 0,
+            combo_multiplier: DECIMAL_SCALE_FACTOR as u64,
+            last_click_reward: PLAYER_STARTING_MONEY,
+            last_click_critical: false,
             upgrades: Vec::new(),
+            unlockable_upgrades: Vec::new(),
             stocks: Vec::new(),
             online: true,
-        });
+        };
+        refresh_unlockable_upgrades(ctx, &mut player);
+        ctx.db.player().insert(player);
     } else if let Some(mut player) = ctx.db.player().identity().find(identity) {
+        settle_income(ctx, &mut player);
         player.online = true;
+        revalue_player(ctx, &mut player);
+        refresh_unlockable_upgrades(ctx, &mut player);
         ctx.db.player().identity().update(player);
     }
 }
@@ -63,7 +115,10 @@ pub fn identity_disconnected(ctx: &ReducerContext) {
 
     if ctx.db.player().identity().find(identity).is_none() {
     } else if let Some(mut player) = ctx.db.player().identity().find(identity) {
+        settle_income(ctx, &mut player);
         player.online = false;
+        player.offline_since = ctx.timestamp;
+        revalue_player(ctx, &mut player);
         ctx.db.player().identity().update(player);
     }
 }
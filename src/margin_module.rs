@@ -0,0 +1,344 @@
+use crate::admin_module::ensure_admin;
+use crate::company_module::{ipo_buyback_available, record_ipo_flow};
+use crate::constants::{DECIMAL_SCALE_FACTOR, MARGIN_INTEREST_PERIOD_MICROS};
+use crate::player_module::{player, settle_income, Player};
+use crate::portfolio_module::revalue_player;
+use crate::stock_module::stock;
+use crate::transaction_module::{ensure_order_type, sell_shares, OrderType};
+use spacetimedb::sats::u256;
+use spacetimedb::{reducer, table, Identity, ReducerContext, Table, TimeDuration, Timestamp};
+use std::cmp::Reverse;
+
+/// Leverage and maintenance margin are scaled by `DECIMAL_SCALE_FACTOR`, so 3_000 is 3x and
+/// 250 requires equity of at least 25% of holdings. Interest is charged per
+/// `MARGIN_INTEREST_PERIOD_MICROS` on the same scale.
+#[table(name = margin_config, public)]
+pub struct MarginConfig {
+    pub max_leverage: u64,
+    pub interest_rate: u64,
+    pub maintenance_margin: u64,
+}
+
+/// Buys that exceed `Player.money` borrow the shortfall here, up to `leverage` times the
+/// player's equity. Interest accrues onto `borrowed` once per elapsed interest period.
+#[table(name = margin_account, public)]
+pub struct MarginAccount {
+    #[primary_key]
+    pub identity: Identity,
+    pub leverage: u64,
+    pub borrowed: u128,
+    pub interest_accrued: u128,
+    pub opened_at: Timestamp,
+    pub last_interest_at: Timestamp,
+}
+
+#[table(name = liquidation, public)]
+pub struct Liquidation {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    #[index(btree)]
+    pub identity: Identity,
+    pub shares_sold: u64,
+    pub proceeds: u128,
+    pub debt_repaid: u128,
+    pub debt_remaining: u128,
+    pub timestamp: Timestamp,
+}
+
+pub fn init_margin_config(
+    ctx: &ReducerContext,
+    max_leverage: u64,
+    interest_rate: u64,
+    maintenance_margin: u64,
+) -> Result<(), String> {
+    if max_leverage < DECIMAL_SCALE_FACTOR as u64 {
+        return Err("Max leverage cannot be below 1x.".to_string());
+    }
+
+    ctx.db.margin_config().insert(MarginConfig {
+        max_leverage,
+        interest_rate,
+        maintenance_margin,
+    });
+
+    Ok(())
+}
+
+#[reducer]
+pub fn set_margin_config(
+    ctx: &ReducerContext,
+    max_leverage: u64,
+    interest_rate: u64,
+    maintenance_margin: u64,
+) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    for config in ctx.db.margin_config().iter() {
+        ctx.db.margin_config().delete(config);
+    }
+
+    init_margin_config(ctx, max_leverage, interest_rate, maintenance_margin)
+}
+
+/// Opens the player's margin account or changes its leverage. A leverage of 1x disables
+/// further borrowing without touching the outstanding loan.
+#[reducer]
+pub fn set_margin_leverage(ctx: &ReducerContext, leverage: u64) -> Result<(), String> {
+    let config = margin_config(ctx)?;
+
+    let Some(player) = ctx.db.player().identity().find(ctx.sender) else {
+        return Err("Player not found.".to_string());
+    };
+
+    ensure_order_type(&player, OrderType::Margin)?;
+
+    if leverage < DECIMAL_SCALE_FACTOR as u64 || leverage > config.max_leverage {
+        return Err("Leverage is outside the allowed range.".to_string());
+    }
+
+    match ctx.db.margin_account().identity().find(ctx.sender) {
+        Some(mut account) => {
+            account.leverage = leverage;
+            ctx.db.margin_account().identity().update(account);
+        }
+        None => {
+            ctx.db.margin_account().insert(MarginAccount {
+                identity: ctx.sender,
+                leverage,
+                borrowed: 0,
+                interest_accrued: 0,
+                opened_at: ctx.timestamp,
+                last_interest_at: ctx.timestamp,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Pays down the margin loan from `Player.money`. Repays the whole loan if `amount` exceeds it.
+#[reducer]
+pub fn repay_margin(ctx: &ReducerContext, amount: u128) -> Result<(), String> {
+    let Some(mut account) = ctx.db.margin_account().identity().find(ctx.sender) else {
+        return Err("No margin account.".to_string());
+    };
+
+    let Some(mut player) = ctx.db.player().identity().find(ctx.sender) else {
+        return Err("Player not found.".to_string());
+    };
+
+    let amount = amount.min(account.borrowed);
+    if amount == 0 {
+        return Err("Nothing to repay.".to_string());
+    }
+
+    settle_income(ctx, &mut player);
+    if player.money < u256::from(amount) {
+        return Err("Not enough money.".to_string());
+    }
+
+    player.money -= u256::from(amount);
+    account.borrowed -= amount;
+
+    ctx.db.margin_account().identity().update(account);
+    revalue_player(ctx, &mut player);
+    ctx.db.player().identity().update(player);
+    Ok(())
+}
+
+/// Lends the player `shortfall` so a buy can go through, if their margin account has room
+/// for it at the chosen leverage. Equity is taken from the player's current net worth.
+pub fn draw_margin(
+    ctx: &ReducerContext,
+    player: &mut Player,
+    shortfall: u256,
+) -> Result<(), String> {
+    let Some(mut account) = ctx.db.margin_account().identity().find(player.identity) else {
+        return Err("Not enough money.".to_string());
+    };
+
+    let scale = u256::from(DECIMAL_SCALE_FACTOR);
+    let limit = player.net_worth
+        * u256::from(account.leverage.saturating_sub(DECIMAL_SCALE_FACTOR as u64))
+        / scale;
+    let borrowed = u256::from(account.borrowed) + shortfall;
+    if borrowed > limit {
+        return Err("Not enough money or margin available.".to_string());
+    }
+
+    if account.borrowed == 0 {
+        account.last_interest_at = ctx.timestamp;
+    }
+    account.borrowed =
+        u128::try_from(borrowed).map_err(|_| "Margin loan is too large.".to_string())?;
+    player.money += shortfall;
+    ctx.db.margin_account().identity().update(account);
+    Ok(())
+}
+
+pub fn margin_debt(ctx: &ReducerContext, identity: Identity) -> u128 {
+    ctx.db
+        .margin_account()
+        .identity()
+        .find(identity)
+        .map_or(0, |a| a.borrowed)
+}
+
+/// Forgives the outstanding loan. Used when a prestige reset wipes the player's assets.
+pub fn clear_margin_debt(ctx: &ReducerContext, identity: Identity) {
+    if let Some(mut account) = ctx.db.margin_account().identity().find(identity) {
+        account.borrowed = 0;
+        account.last_interest_at = ctx.timestamp;
+        ctx.db.margin_account().identity().update(account);
+    }
+}
+
+/// Accrues interest for every whole interest period on each outstanding loan and liquidates
+/// accounts whose equity has fallen below the maintenance margin. Runs after repricing in
+/// `update_stock_prices`.
+pub fn settle_margin_accounts(ctx: &ReducerContext) {
+    let Ok(config) = margin_config(ctx) else {
+        return;
+    };
+
+    for mut account in ctx.db.margin_account().iter().filter(|a| a.borrowed > 0) {
+        // Only whole periods are billed, and the clock advances by exactly the time billed,
+        // so short ticks accumulate instead of truncating the interest to zero.
+        let periods = ctx
+            .timestamp
+            .time_duration_since(account.last_interest_at)
+            .map_or(0, |d| {
+                d.to_micros().max(0) as u64 / MARGIN_INTEREST_PERIOD_MICROS
+            });
+        if periods > 0 {
+            let interest = u256::from(account.borrowed)
+                * u256::from(config.interest_rate)
+                * u256::from(periods)
+                / u256::from(DECIMAL_SCALE_FACTOR);
+            let interest = u128::try_from(interest).unwrap_or(u128::MAX);
+            account.borrowed = account.borrowed.saturating_add(interest);
+            account.interest_accrued = account.interest_accrued.saturating_add(interest);
+            account.last_interest_at +=
+                TimeDuration::from_micros((periods * MARGIN_INTEREST_PERIOD_MICROS) as i64);
+        }
+
+        if let Some(mut player) = ctx.db.player().identity().find(account.identity) {
+            if below_maintenance(ctx, &player, account.borrowed, config.maintenance_margin) {
+                settle_income(ctx, &mut player);
+                liquidate(ctx, &mut player, &mut account, config.maintenance_margin);
+                revalue_player(ctx, &mut player);
+                ctx.db.player().identity().update(player);
+            }
+        }
+
+        ctx.db.margin_account().identity().update(account);
+    }
+}
+
+/// Sells positions into the house pool, largest first, repaying the loan from the proceeds
+/// until the account is back above the maintenance margin or the loan is cleared. Nothing is
+/// logged when no position could be sold.
+fn liquidate(
+    ctx: &ReducerContext,
+    player: &mut Player,
+    account: &mut MarginAccount,
+    maintenance_margin: u64,
+) {
+    let mut positions: Vec<(u16, u64, u128)> = player
+        .stocks
+        .iter()
+        .filter_map(|s| {
+            let stock = ctx.db.stock().id().find(s.stock_id)?;
+            Some((s.stock_id, s.amount, stock.price_per_share))
+        })
+        .collect();
+    positions.sort_by_key(|&(_, amount, price)| Reverse(u256::from(price) * u256::from(amount)));
+
+    let mut shares_sold = 0;
+    let mut proceeds = 0u128;
+    let mut debt_repaid = 0u128;
+
+    for (stock_id, amount, price) in positions {
+        if account.borrowed == 0
+            || !below_maintenance(ctx, player, account.borrowed, maintenance_margin)
+        {
+            break;
+        }
+
+        let Some(mut stock) = ctx.db.stock().id().find(stock_id) else {
+            continue;
+        };
+        let Some(total_price) = price.checked_mul(amount.into()) else {
+            continue;
+        };
+        if !ipo_buyback_available(ctx, stock_id, total_price) {
+            continue;
+        }
+
+        let money_before = player.money;
+        if sell_shares(player, stock_id, amount, total_price).is_err() {
+            continue;
+        }
+        record_ipo_flow(ctx, stock_id, 0, total_price);
+        let received = u128::try_from(player.money - money_before).unwrap_or(u128::MAX);
+
+        stock.available_shares += amount;
+        stock.recent_sells += amount;
+        ctx.db.stock().id().update(stock);
+
+        let repay = u128::try_from(player.money)
+            .unwrap_or(u128::MAX)
+            .min(account.borrowed);
+        player.money -= u256::from(repay);
+        account.borrowed -= repay;
+
+        shares_sold += amount;
+        proceeds += received;
+        debt_repaid += repay;
+    }
+
+    if shares_sold == 0 {
+        return;
+    }
+
+    log::info!("Liquidated margin account of {}", player.identity);
+    ctx.db.liquidation().insert(Liquidation {
+        id: 0,
+        identity: player.identity,
+        shares_sold,
+        proceeds,
+        debt_repaid,
+        debt_remaining: account.borrowed,
+        timestamp: ctx.timestamp,
+    });
+}
+
+/// Equity is cash plus holdings at current prices minus the loan; the account is below the
+/// maintenance margin when equity is less than `maintenance_margin` of the holdings' value.
+fn below_maintenance(
+    ctx: &ReducerContext,
+    player: &Player,
+    borrowed: u128,
+    maintenance_margin: u64,
+) -> bool {
+    let holdings = player
+        .stocks
+        .iter()
+        .filter_map(|s| {
+            let stock = ctx.db.stock().id().find(s.stock_id)?;
+            Some(u256::from(stock.price_per_share) * u256::from(s.amount))
+        })
+        .fold(u256::new(0), |total, value| total + value);
+    let equity = (player.money + holdings).saturating_sub(u256::from(borrowed));
+
+    equity * u256::from(DECIMAL_SCALE_FACTOR) < holdings * u256::from(maintenance_margin)
+}
+
+fn margin_config(ctx: &ReducerContext) -> Result<MarginConfig, String> {
+    ctx.db
+        .margin_config()
+        .iter()
+        .next()
+        .ok_or("Margin configuration not initialized.".to_string())
+}
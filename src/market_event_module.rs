@@ -0,0 +1,195 @@
+use crate::admin_module::ensure_admin;
+use crate::constants::{DECIMAL_SCALE_FACTOR, MARKET_EVENT_INTERVAL_MICROS, NEWS_RETENTION_MICROS};
+use crate::price_model_module::bound_price;
+use crate::stock_module::{market_config, stock};
+use spacetimedb::rand::Rng;
+use spacetimedb::{reducer, table, ReducerContext, ScheduleAt, Table, TimeDuration, Timestamp};
+use std::time::Duration;
+
+/// Catalog entry. `price_shock` is applied once when the event fires and `drift` every tick
+/// until the news expires; both are scaled by `DECIMAL_SCALE_FACTOR`.
+#[table(name = market_event, public)]
+pub struct MarketEvent {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u16,
+    pub stock_id: u16,
+    pub headline: String,
+    pub price_shock: i64,
+    pub drift: i64,
+    pub duration_micros: i64,
+    pub weight: u32,
+}
+
+#[table(name = news, public)]
+pub struct News {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub event_id: u16,
+    #[index(btree)]
+    pub stock_id: u16,
+    pub headline: String,
+    pub drift: i64,
+    pub published_at: Timestamp,
+    pub expires_at: Timestamp,
+}
+
+#[table(name = market_event_schedule, scheduled(trigger_market_event))]
+pub struct MarketEventSchedule {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub scheduled_at: ScheduleAt,
+}
+
+#[reducer]
+pub fn add_market_event(
+    ctx: &ReducerContext,
+    stock_id: u16,
+    headline: String,
+    price_shock: i64,
+    drift: i64,
+    duration_micros: i64,
+    weight: u32,
+) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    if ctx.db.stock().id().find(stock_id).is_none() {
+        return Err("Stock not found.".to_string());
+    }
+
+    if weight == 0 {
+        return Err("Event weight cannot be zero.".to_string());
+    }
+
+    if duration_micros < 0 {
+        return Err("Event duration cannot be negative.".to_string());
+    }
+
+    ctx.db.market_event().insert(MarketEvent {
+        id: 0,
+        stock_id,
+        headline,
+        price_shock,
+        drift,
+        duration_micros,
+        weight,
+    });
+    Ok(())
+}
+
+#[reducer]
+pub fn remove_market_event(ctx: &ReducerContext, event_id: u16) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    if !ctx.db.market_event().id().delete(event_id) {
+        return Err("Market event not found.".to_string());
+    }
+    Ok(())
+}
+
+#[reducer]
+pub fn trigger_market_event(ctx: &ReducerContext, _args: MarketEventSchedule) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    if let Some(event) = pick_market_event(ctx) {
+        publish_market_event(ctx, event)?;
+    }
+
+    prune_news(ctx);
+
+    ctx.db.market_event_schedule().insert(MarketEventSchedule {
+        id: 0,
+        scheduled_at: (ctx.timestamp + Duration::from_micros(MARKET_EVENT_INTERVAL_MICROS)).into(),
+    });
+
+    Ok(())
+}
+
+/// Applies the drift of every unexpired news item for the stock on top of a repriced value.
+pub fn apply_event_drift(ctx: &ReducerContext, stock_id: u16, price: u128) -> u128 {
+    let drift: i128 = ctx
+        .db
+        .news()
+        .stock_id()
+        .filter(stock_id)
+        .filter(|n| n.expires_at > ctx.timestamp)
+        .map(|n| n.drift as i128)
+        .sum();
+
+    if drift == 0 {
+        return price;
+    }
+
+    scale_price(ctx, price, drift)
+}
+
+fn pick_market_event(ctx: &ReducerContext) -> Option<MarketEvent> {
+    let total_weight: u64 = ctx.db.market_event().iter().map(|e| e.weight as u64).sum();
+    if total_weight == 0 {
+        return None;
+    }
+
+    let mut roll = ctx.rng().gen_range(0..total_weight);
+    for event in ctx.db.market_event().iter() {
+        if roll < event.weight as u64 {
+            return Some(event);
+        }
+        roll -= event.weight as u64;
+    }
+    None
+}
+
+fn publish_market_event(ctx: &ReducerContext, event: MarketEvent) -> Result<(), String> {
+    let Some(mut stock) = ctx.db.stock().id().find(event.stock_id) else {
+        return Err("Stock for market event not found.".to_string());
+    };
+
+    let new_price = scale_price(ctx, stock.price_per_share, event.price_shock as i128);
+    stock.last_price = stock.price_per_share;
+    stock.price_per_share = new_price;
+    ctx.db.stock().id().update(stock);
+
+    let expires_at = ctx
+        .timestamp
+        .checked_add(TimeDuration::from_micros(event.duration_micros))
+        .ok_or("Failed to calculate news expiry.")?;
+
+    ctx.db.news().insert(News {
+        id: 0,
+        event_id: event.id,
+        stock_id: event.stock_id,
+        headline: event.headline,
+        drift: event.drift,
+        published_at: ctx.timestamp,
+        expires_at,
+    });
+    Ok(())
+}
+
+fn prune_news(ctx: &ReducerContext) {
+    let cutoff = ctx.timestamp.to_micros_since_unix_epoch() - NEWS_RETENTION_MICROS;
+    let expired: Vec<u64> = ctx
+        .db
+        .news()
+        .iter()
+        .filter(|n| n.expires_at.to_micros_since_unix_epoch() < cutoff)
+        .map(|n| n.id)
+        .collect();
+
+    for id in expired {
+        ctx.db.news().id().delete(id);
+    }
+}
+
+fn scale_price(ctx: &ReducerContext, price: u128, change_scaled: i128) -> u128 {
+    let scale = DECIMAL_SCALE_FACTOR as i128;
+    let price = i128::try_from(price).unwrap_or(i128::MAX);
+    let scaled = price.saturating_add(price.saturating_mul(change_scaled) / scale);
+
+    match ctx.db.market_config().iter().next() {
+        Some(config) => bound_price(scaled, &config),
+        None => scaled.max(0) as u128,
+    }
+}
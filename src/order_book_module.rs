@@ -0,0 +1,247 @@
+use crate::achievement_module::record_stats;
+use crate::constants::{DECIMAL_SCALE_FACTOR, ORDER_BOOK_PRICE_BAND};
+use crate::margin_module::margin_debt;
+use crate::player_module::{player, settle_income};
+use crate::portfolio_module::revalue_player;
+use crate::stock_module::{stock, Stock};
+use crate::transaction_module::{
+    buy_shares, remaining_amount, sell_shares, transaction, Transaction, TransactionStatus,
+    TransactionType,
+};
+use spacetimedb::{table, Identity, ReducerContext, Table, Timestamp};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+/// A single fill. A missing counterparty means that side was filled by the house pool.
+#[table(name = trade, public)]
+pub struct Trade {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    #[index(btree)]
+    pub stock_id: u16,
+    pub buyer: Option<Identity>,
+    pub seller: Option<Identity>,
+    pub buy_order: Option<u64>,
+    pub sell_order: Option<u64>,
+    pub amount: u64,
+    pub price: u128,
+    pub timestamp: Timestamp,
+}
+
+pub fn record_trade(
+    ctx: &ReducerContext,
+    stock_id: u16,
+    buy: Option<&Transaction>,
+    sell: Option<&Transaction>,
+    amount: u64,
+    price: u128,
+) {
+    ctx.db.trade().insert(Trade {
+        id: 0,
+        stock_id,
+        buyer: buy.map(|tx| tx.sender),
+        seller: sell.map(|tx| tx.sender),
+        buy_order: buy.map(|tx| tx.id),
+        sell_order: sell.map(|tx| tx.id),
+        amount,
+        price,
+        timestamp: ctx.timestamp,
+    });
+}
+
+/// Matches the pending orders of one stock against each other by price-time priority: best
+/// price first, then the earliest order, with market orders ahead of limits at the same price.
+/// Market orders are treated as limits at the current share price, so they never fill worse
+/// than the house would. A fill executes at the older order's limit, or the current share
+/// price when neither side has one, clamped to `ORDER_BOOK_PRICE_BAND` around the current
+/// share price; orders that cannot cross inside the band are left to the house. Orders are
+/// never matched against another order from the same player, and an order whose fill fails
+/// stays pending for the house fallback.
+pub fn match_order_book(ctx: &ReducerContext, mut stock: Stock) {
+    let (mut bids, mut asks): (Vec<Transaction>, Vec<Transaction>) = ctx
+        .db
+        .transaction()
+        .stock_id()
+        .filter(stock.id)
+        .filter(|t| t.status == TransactionStatus::Pending)
+        .partition(|t| t.tx_type == TransactionType::Buy);
+
+    if bids.is_empty() || asks.is_empty() {
+        return;
+    }
+
+    let house_price = stock.price_per_share;
+    let limit_of = |tx: &Transaction| tx.limit_price.unwrap_or(house_price);
+    let band = house_price.saturating_mul(ORDER_BOOK_PRICE_BAND) / DECIMAL_SCALE_FACTOR;
+    let (band_low, band_high) = (
+        house_price.saturating_sub(band),
+        house_price.saturating_add(band),
+    );
+    bids.sort_by_key(|t| {
+        (
+            Reverse(limit_of(t)),
+            t.limit_price.is_some(),
+            t.timestamp,
+            t.id,
+        )
+    });
+    asks.sort_by_key(|t| (limit_of(t), t.limit_price.is_some(), t.timestamp, t.id));
+
+    let mut filled = false;
+    let mut unfillable_asks = HashSet::new();
+    for bid in bids.iter_mut() {
+        for ask in asks.iter_mut() {
+            if bid.status != TransactionStatus::Pending {
+                break;
+            }
+
+            if ask.status != TransactionStatus::Pending
+                || ask.sender == bid.sender
+                || unfillable_asks.contains(&ask.id)
+            {
+                continue;
+            }
+
+            if limit_of(ask) > limit_of(bid) {
+                break;
+            }
+
+            let price = match (bid.limit_price, ask.limit_price) {
+                (Some(bid_limit), Some(ask_limit)) => {
+                    if (bid.timestamp, bid.id) < (ask.timestamp, ask.id) {
+                        bid_limit
+                    } else {
+                        ask_limit
+                    }
+                }
+                (Some(limit), None) | (None, Some(limit)) => limit,
+                (None, None) => house_price,
+            }
+            .clamp(band_low, band_high);
+            if price > limit_of(bid) || price < limit_of(ask) {
+                continue;
+            }
+            let amount = remaining_amount(bid).min(remaining_amount(ask));
+
+            match execute_fill(ctx, &mut stock, bid, ask, amount, price) {
+                Ok(()) => filled = true,
+                Err(TransactionType::Buy) => break,
+                Err(TransactionType::Sell) => {
+                    unfillable_asks.insert(ask.id);
+                }
+            }
+        }
+    }
+
+    for tx in bids.into_iter().chain(asks) {
+        ctx.db.transaction().id().update(tx);
+    }
+
+    if filled {
+        ctx.db.stock().id().update(stock);
+    }
+}
+
+/// Moves shares from the seller to the buyer at `price`. If either side cannot cover its part
+/// of the fill, nothing changes hands and the failing side is returned so the caller can
+/// leave that order to the house fallback.
+fn execute_fill(
+    ctx: &ReducerContext,
+    stock: &mut Stock,
+    bid: &mut Transaction,
+    ask: &mut Transaction,
+    amount: u64,
+    price: u128,
+) -> Result<(), TransactionType> {
+    let Some(mut seller) = ctx.db.player().identity().find(ask.sender) else {
+        return Err(TransactionType::Sell);
+    };
+
+    let Some(mut buyer) = ctx.db.player().identity().find(bid.sender) else {
+        return Err(TransactionType::Buy);
+    };
+
+    let Some(total_price) = price.checked_mul(amount.into()) else {
+        return Err(TransactionType::Buy);
+    };
+
+    if bid.amount > buyer.max_order_shares {
+        return Err(TransactionType::Buy);
+    }
+
+    if ask.amount > seller.max_order_shares {
+        return Err(TransactionType::Sell);
+    }
+
+    // Players with a margin loan only trade at the house price, so the book cannot be used
+    // to move borrowed money to another account.
+    if price != stock.price_per_share {
+        if margin_debt(ctx, buyer.identity) > 0 {
+            return Err(TransactionType::Buy);
+        }
+
+        if margin_debt(ctx, seller.identity) > 0 {
+            return Err(TransactionType::Sell);
+        }
+    }
+
+    let held = seller
+        .stocks
+        .iter()
+        .find(|s| s.stock_id == stock.id)
+        .map_or(0, |s| s.amount);
+    if held < amount {
+        return Err(TransactionType::Sell);
+    }
+
+    settle_income(ctx, &mut buyer);
+    settle_income(ctx, &mut seller);
+
+    // The sell leg only touches the in-memory seller, so it runs first and is undone from
+    // this snapshot if the buyer cannot pay.
+    let snapshot = (seller.money, seller.stocks.clone(), seller.realized_pnl);
+    let pnl = match sell_shares(&mut seller, stock.id, amount, total_price) {
+        Ok(pnl) => pnl,
+        Err(reason) => {
+            log::debug!("Could not match transaction {}: {}", ask.id, reason);
+            ctx.db.player().identity().update(buyer);
+            ctx.db.player().identity().update(seller);
+            return Err(TransactionType::Sell);
+        }
+    };
+
+    if let Err(reason) = buy_shares(ctx, &mut buyer, stock.id, amount, total_price) {
+        log::debug!("Could not match transaction {}: {}", bid.id, reason);
+        (seller.money, seller.stocks, seller.realized_pnl) = snapshot;
+        ctx.db.player().identity().update(buyer);
+        ctx.db.player().identity().update(seller);
+        return Err(TransactionType::Buy);
+    }
+
+    if amount > 0 {
+        record_stats(ctx, &mut buyer, |stats| stats.trades_executed += 1);
+        record_stats(ctx, &mut seller, |stats| {
+            stats.trades_executed += 1;
+            stats.biggest_single_profit = stats.biggest_single_profit.max(pnl);
+        });
+    }
+
+    // Shares change hands without touching the pool, so demand stays balanced.
+    stock.recent_buys += amount;
+    stock.recent_sells += amount;
+
+    for tx in [&mut *bid, &mut *ask] {
+        tx.filled_amount += amount;
+        if tx.filled_amount == tx.amount {
+            tx.status = TransactionStatus::Confirmed;
+        }
+    }
+    record_trade(ctx, stock.id, Some(bid), Some(ask), amount, price);
+
+    revalue_player(ctx, &mut buyer);
+    revalue_player(ctx, &mut seller);
+    ctx.db.player().identity().update(buyer);
+    ctx.db.player().identity().update(seller);
+    Ok(())
+}
@@ -1,12 +1,30 @@
-use crate::constants::PLAYER_UPDATE_INTERVAL_MICROS;
+use crate::achievement_module::record_stats;
+use crate::boost_module::{click_boosts, passive_boost_bonus};
+use crate::anti_cheat_module::{ensure_not_banned, inspect_click};
+use crate::constants::{
+    DECIMAL_SCALE_FACTOR, PLAYER_INCOME_PERIOD_MICROS, PLAYER_MIN_CLICK_INTERVAL_MICROS, PLAYER_OFFLINE_EARNINGS_CAP_MICROS,
+    PLAYER_OFFLINE_EARNINGS_MULTIPLIER,
+};
+use crate::portfolio_module::revalue_player;
+use crate::transaction_module::OrderType;
+use crate::upgrades_module::refresh_unlockable_upgrades;
+use spacetimedb::rand::Rng;
 use spacetimedb::sats::u256;
-use spacetimedb::{reducer, table, Identity, ReducerContext, ScheduleAt, SpacetimeType, Table, TimeDuration, Timestamp};
-use std::time::Duration;
+use spacetimedb::{reducer, table, Identity, ReducerContext, SpacetimeType, TimeDuration, Timestamp};
 
-#[derive(SpacetimeType)]
+#[derive(SpacetimeType, Clone)]
 pub struct StockType{
     pub stock_id: u16,
     pub amount: u64,
+    pub cost_basis: u128,
+    pub realized_pnl: i128,
+    pub unrealized_pnl: i128,
+}
+
+#[derive(SpacetimeType)]
+pub struct OwnedUpgrade {
+    pub upgrade_id: u16,
+    pub level: u8,
 }
 
 #[table(name = player, public)]
@@ -15,39 +33,71 @@ pub struct Player {
     pub identity: Identity,
     pub username: Option<String>,
     pub money: u256,
+    pub net_worth: u256,
+    pub realized_pnl: i128,
     pub passive_income: u128,
     pub click_power: u128,
     pub click_timer: i64,
     pub stock_buy_fee: u16,
     pub stock_sell_fee: u16,
+    pub max_order_shares: u64,
+    pub unlocked_order_types: Vec<OrderType>,
     pub online: bool,
-    pub upgrades: Vec<u16>,
+    pub upgrades: Vec<OwnedUpgrade>,
+    pub unlockable_upgrades: Vec<u16>,
     pub stocks: Vec<StockType>,
     pub last_click: Timestamp,
-}
-#[table(name = update_player_schedule, scheduled(update_players))]
-pub struct UpdatePlayersSchedule {
-    #[primary_key]
-    #[auto_inc]
-    pub id: u64,
-    pub scheduled_at: ScheduleAt,
+    pub combo_window: i64,
+    pub combo_step: u64,
+    pub combo_max_multiplier: u64,
+    pub crit_chance: u64,
+    pub crit_multiplier: u64,
+    pub combo_count: u32,
+    pub combo_multiplier: u64,
+    pub last_click_reward: u256,
+    pub last_click_critical: bool,
+    pub last_income_at: Timestamp,
+    pub offline_since: Timestamp,
 }
 
-#[reducer]
-pub fn update_players(ctx: &ReducerContext, _args: UpdatePlayersSchedule) -> Result<(), String> {
-    for mut player in ctx.db.player().iter() {
-        if player.passive_income > 0 {
-            player.money += u256::from(player.passive_income);
-            ctx.db.player().identity().update(player);
+/// Credits passive income accrued since `last_income_at`. Time spent offline is paid at the
+/// offline multiplier, and only up to the cap measured from `offline_since`, however many
+/// times an offline player is settled. The caller is responsible for persisting the row.
+pub fn settle_income(ctx: &ReducerContext, player: &mut Player) {
+    let Some(elapsed) = ctx.timestamp.time_duration_since(player.last_income_at) else {
+        return;
+    };
+    let from = player.last_income_at;
+    player.last_income_at = ctx.timestamp;
+
+    let mut elapsed_micros = elapsed.to_micros();
+    let mut multiplier = DECIMAL_SCALE_FACTOR;
+    if !player.online {
+        if let Some(cap) = PLAYER_OFFLINE_EARNINGS_CAP_MICROS {
+            let cap_end = player.offline_since.to_micros_since_unix_epoch().saturating_add(cap);
+            let paid_until = ctx.timestamp.to_micros_since_unix_epoch().min(cap_end);
+            elapsed_micros = (paid_until - from.to_micros_since_unix_epoch()).max(0);
         }
+        multiplier = PLAYER_OFFLINE_EARNINGS_MULTIPLIER;
     }
 
-    ctx.db.update_player_schedule().insert(UpdatePlayersSchedule {
-        id: 0,
-        scheduled_at: (ctx.timestamp + Duration::from_micros(PLAYER_UPDATE_INTERVAL_MICROS)).into(),
-    });
+    let boosted_micros = passive_boost_bonus(ctx, player.identity, from, ctx.timestamp);
+    if player.passive_income == 0 || elapsed_micros <= 0 {
+        return;
+    }
 
-    Ok(())
+    // Boost time is shrunk in proportion when the offline cap cuts the elapsed window.
+    let scale = u256::from(DECIMAL_SCALE_FACTOR);
+    let boosted_micros = boosted_micros * u256::from(elapsed_micros as u64)
+        / u256::from(elapsed.to_micros().max(1) as u64);
+    let weighted_micros = u256::from(elapsed_micros as u64) * scale + boosted_micros;
+    let earned = u256::from(player.passive_income)
+        * weighted_micros
+        * u256::from(multiplier)
+        / u256::from(PLAYER_INCOME_PERIOD_MICROS as u64)
+        / (scale * scale);
+    player.money += earned;
+    record_stats(ctx, player, |stats| stats.lifetime_earnings += earned);
 }
 
 #[reducer]
@@ -71,16 +121,49 @@ pub fn set_name(ctx: &ReducerContext, username: String) -> Result<(), String> {
 #[reducer]
 pub fn increase_money(ctx: &ReducerContext) -> Result<(), String> {
     if let Some(mut player) = ctx.db.player().identity().find(ctx.sender) {
-        let duration = TimeDuration::from_micros(player.click_timer);
+        ensure_not_banned(ctx, ctx.sender)?;
+
+        let (boost, cooldown_reduction) = click_boosts(ctx, ctx.sender);
+        let cooldown = (player.click_timer as i128
+            * (DECIMAL_SCALE_FACTOR - cooldown_reduction as u128) as i128
+            / DECIMAL_SCALE_FACTOR as i128) as i64;
+        let cooldown = cooldown.max(PLAYER_MIN_CLICK_INTERVAL_MICROS);
+        let duration = TimeDuration::from_micros(cooldown);
         let now = ctx.timestamp;
 
         match player.last_click.checked_add(duration) {
             Some(allowed_time) if now < allowed_time => {
                 Err("Clicking too fast. Wait for the timer.".to_string())
             }
-            Some(_) => {
-                player.money += u256::from(player.click_power);
+            Some(allowed_time) => {
+                settle_income(ctx, &mut player);
+                let penalty = inspect_click(ctx, &player, cooldown);
+                let combo = advance_combo(&mut player, allowed_time, now);
+                let critical =
+                    ctx.rng().gen_range(0..DECIMAL_SCALE_FACTOR as u64) < player.crit_chance;
+                let crit = if critical {
+                    player.crit_multiplier as u128
+                } else {
+                    DECIMAL_SCALE_FACTOR
+                };
+
+                let scale = u256::from(DECIMAL_SCALE_FACTOR);
+                let reward = u256::from(player.click_power)
+                    * u256::from(combo)
+                    * u256::from(crit)
+                    * u256::from(boost)
+                    * u256::from(penalty)
+                    / (scale * scale * scale * scale);
+                player.money += reward;
                 player.last_click = now;
+                player.last_click_reward = reward;
+                player.last_click_critical = critical;
+                record_stats(ctx, &mut player, |stats| {
+                    stats.total_clicks += 1;
+                    stats.lifetime_earnings += reward;
+                });
+                revalue_player(ctx, &mut player);
+                refresh_unlockable_upgrades(ctx, &mut player);
                 ctx.db.player().identity().update(player);
                 Ok(())
             }
@@ -89,4 +172,18 @@ pub fn increase_money(ctx: &ReducerContext) -> Result<(), String> {
     } else {
         Err("Cannot reward bonus for unknown player".to_string())
     }
-}
\ No newline at end of file
+}
+
+/// Chains the click onto the current combo if it lands within `combo_window` of the cooldown
+/// expiring, otherwise starts a new combo. Returns the multiplier scaled by `DECIMAL_SCALE_FACTOR`.
+fn advance_combo(player: &mut Player, allowed_time: Timestamp, now: Timestamp) -> u64 {
+    let in_window = now
+        .time_duration_since(allowed_time)
+        .is_some_and(|late| late.to_micros() <= player.combo_window);
+
+    player.combo_count = if in_window { player.combo_count.saturating_add(1) } else { 0 };
+    player.combo_multiplier = (DECIMAL_SCALE_FACTOR as u64)
+        .saturating_add(player.combo_step.saturating_mul(player.combo_count as u64))
+        .min(player.combo_max_multiplier.max(DECIMAL_SCALE_FACTOR as u64));
+    player.combo_multiplier
+}
@@ -0,0 +1,144 @@
+use crate::margin_module::margin_debt;
+use crate::player_module::{player, Player};
+use crate::short_module::{short_exposure, short_position, ShortStatus};
+use crate::stock_module::stock;
+use crate::upgrades_module::refresh_unlockable_upgrades;
+use spacetimedb::sats::u256;
+use spacetimedb::{table, Identity, ReducerContext, Table};
+use std::collections::{HashMap, HashSet};
+
+/// Index of which players hold which stocks, mirrored from `Player.stocks` by
+/// `revalue_player` so repricing only has to visit the affected holders.
+#[table(name = holding, public)]
+pub struct Holding {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    #[index(btree)]
+    pub identity: Identity,
+    #[index(btree)]
+    pub stock_id: u16,
+}
+
+/// Marks the holdings and open shorts of every player exposed to a `repriced` stock to the
+/// current `price_per_share`, writing only the rows whose valuation changed. Every reducer
+/// that changes money calls `revalue_player` itself, which also refreshes unlockable upgrades.
+pub fn revalue_portfolios(ctx: &ReducerContext, repriced: &[u16]) {
+    if repriced.is_empty() {
+        return;
+    }
+
+    let prices = current_prices(ctx);
+    let mut exposed = HashSet::new();
+    for &stock_id in repriced {
+        exposed.extend(ctx.db.holding().stock_id().filter(stock_id).map(|h| h.identity));
+        exposed.extend(
+            ctx.db
+                .short_position()
+                .stock_id()
+                .filter(stock_id)
+                .filter(|p| p.status == ShortStatus::Open)
+                .map(|p| p.owner),
+        );
+    }
+
+    for identity in exposed {
+        let Some(mut player) = ctx.db.player().identity().find(identity) else {
+            continue;
+        };
+
+        let shorts = short_exposure(ctx, player.identity, |id| price_of(&prices, id));
+        let debt = margin_debt(ctx, player.identity);
+        if apply_valuation(&mut player, &prices, shorts, debt) {
+            ctx.db.player().identity().update(player);
+        }
+    }
+}
+
+/// Revalues a single player in place and syncs their `holding` rows; the caller is
+/// responsible for persisting the row.
+pub fn revalue_player(ctx: &ReducerContext, player: &mut Player) {
+    sync_holdings(ctx, player);
+
+    let prices = current_prices(ctx);
+    let shorts = short_exposure(ctx, player.identity, |id| price_of(&prices, id));
+    let debt = margin_debt(ctx, player.identity);
+    if apply_valuation(player, &prices, shorts, debt) {
+        refresh_unlockable_upgrades(ctx, player);
+    }
+}
+
+fn sync_holdings(ctx: &ReducerContext, player: &Player) {
+    let held: HashSet<u16> = player
+        .stocks
+        .iter()
+        .filter(|s| s.amount > 0)
+        .map(|s| s.stock_id)
+        .collect();
+
+    let mut indexed = HashSet::new();
+    for holding in ctx.db.holding().identity().filter(player.identity) {
+        if held.contains(&holding.stock_id) && indexed.insert(holding.stock_id) {
+            continue;
+        }
+        ctx.db.holding().id().delete(holding.id);
+    }
+
+    for stock_id in held.difference(&indexed) {
+        ctx.db.holding().insert(Holding {
+            id: 0,
+            identity: player.identity,
+            stock_id: *stock_id,
+        });
+    }
+}
+
+fn current_prices(ctx: &ReducerContext) -> HashMap<u16, u128> {
+    ctx.db
+        .stock()
+        .iter()
+        .map(|s| (s.id, s.price_per_share))
+        .collect()
+}
+
+fn price_of(prices: &HashMap<u16, u128>, stock_id: u16) -> u128 {
+    prices.get(&stock_id).copied().unwrap_or(0)
+}
+
+/// `shorts` is `(collateral, liability)`. Short liabilities and margin debt can reduce net
+/// worth down to zero.
+fn apply_valuation(
+    player: &mut Player,
+    prices: &HashMap<u16, u128>,
+    (short_collateral, short_liability): (u256, u256),
+    margin_debt: u128,
+) -> bool {
+    let mut changed = false;
+    let mut net_worth = player.money;
+
+    for position in player.stocks.iter_mut() {
+        let price = price_of(prices, position.stock_id);
+        let market_value = u256::from(price) * u256::from(position.amount);
+        net_worth += market_value;
+
+        let unrealized_pnl = to_i128(market_value) - to_i128(u256::from(position.cost_basis));
+        if position.unrealized_pnl != unrealized_pnl {
+            position.unrealized_pnl = unrealized_pnl;
+            changed = true;
+        }
+    }
+
+    let net_worth = (net_worth + short_collateral)
+        .saturating_sub(short_liability)
+        .saturating_sub(u256::from(margin_debt));
+    if player.net_worth != net_worth {
+        player.net_worth = net_worth;
+        changed = true;
+    }
+
+    changed
+}
+
+fn to_i128(value: u256) -> i128 {
+    i128::try_from(value).unwrap_or(i128::MAX)
+}
@@ -0,0 +1,157 @@
+use crate::constants::{PLAYER_STARTING_MONEY, PRESTIGE_BONUS_PER_POINT, PRESTIGE_POINT_DIVISOR};
+use crate::margin_module::clear_margin_debt;
+use crate::player_module::{player, settle_income};
+use crate::portfolio_module::revalue_player;
+use crate::short_module::release_short_positions;
+use crate::stock_module::stock;
+use crate::transaction_module::{
+    protective_order, transaction, ProtectiveOrderStatus, TransactionStatus,
+};
+use crate::upgrades_module::{recompute_player_stats, refresh_unlockable_upgrades};
+use spacetimedb::sats::u256;
+use spacetimedb::{reducer, table, Identity, ReducerContext, SpacetimeType, Table, Timestamp};
+
+#[derive(SpacetimeType, PartialEq, Clone, Copy)]
+pub enum PrestigeBonus {
+    ClickPower,
+    PassiveIncome,
+}
+
+/// Survives prestige resets. Bonuses are percentage multipliers scaled by
+/// `DECIMAL_SCALE_FACTOR` and are folded into the player's stats as multiplicative effects.
+#[table(name = prestige_record, public)]
+pub struct PrestigeRecord {
+    #[primary_key]
+    pub identity: Identity,
+    pub prestige_count: u32,
+    pub prestige_points: u64,
+    pub lifetime_points: u64,
+    pub click_power_bonus: u64,
+    pub passive_income_bonus: u64,
+    pub last_prestige_at: Option<Timestamp>,
+}
+
+/// Resets money, upgrades and holdings to their starting values in exchange for prestige
+/// points, which scale with the square root of current net worth.
+#[reducer]
+pub fn prestige(ctx: &ReducerContext) -> Result<(), String> {
+    let Some(mut player) = ctx.db.player().identity().find(ctx.sender) else {
+        return Err("Player not found.".to_string());
+    };
+
+    settle_income(ctx, &mut player);
+    revalue_player(ctx, &mut player);
+
+    let points = prestige_points_for(player.net_worth);
+    if points == 0 {
+        return Err("Not enough net worth to prestige.".to_string());
+    }
+
+    for tx in ctx
+        .db
+        .transaction()
+        .iter()
+        .filter(|t| t.sender == ctx.sender && t.status == TransactionStatus::Pending)
+    {
+        let mut tx = tx;
+        tx.status = TransactionStatus::Cancelled;
+        ctx.db.transaction().id().update(tx);
+    }
+
+    for mut order in ctx
+        .db
+        .protective_order()
+        .owner()
+        .filter(ctx.sender)
+        .filter(|o| o.status == ProtectiveOrderStatus::Active)
+    {
+        order.status = ProtectiveOrderStatus::Cancelled;
+        ctx.db.protective_order().id().update(order);
+    }
+
+    release_short_positions(ctx, ctx.sender);
+    clear_margin_debt(ctx, ctx.sender);
+    for position in player.stocks.drain(..) {
+        if let Some(mut stock) = ctx.db.stock().id().find(position.stock_id) {
+            stock.available_shares += position.amount;
+            ctx.db.stock().id().update(stock);
+        }
+    }
+
+    let mut record = ctx.db.prestige_record().identity().find(ctx.sender).unwrap_or(PrestigeRecord {
+        identity: ctx.sender,
+        prestige_count: 0,
+        prestige_points: 0,
+        lifetime_points: 0,
+        click_power_bonus: 0,
+        passive_income_bonus: 0,
+        last_prestige_at: None,
+    });
+    record.prestige_count += 1;
+    record.prestige_points += points;
+    record.lifetime_points += points;
+    record.last_prestige_at = Some(ctx.timestamp);
+    upsert_prestige(ctx, record);
+
+    player.money = PLAYER_STARTING_MONEY;
+    player.upgrades.clear();
+    recompute_player_stats(ctx, &mut player);
+    revalue_player(ctx, &mut player);
+    refresh_unlockable_upgrades(ctx, &mut player);
+    ctx.db.player().identity().update(player);
+    Ok(())
+}
+
+#[reducer]
+pub fn spend_prestige_points(
+    ctx: &ReducerContext,
+    bonus: PrestigeBonus,
+    points: u64,
+) -> Result<(), String> {
+    let Some(mut record) = ctx.db.prestige_record().identity().find(ctx.sender) else {
+        return Err("No prestige points available.".to_string());
+    };
+
+    if points == 0 {
+        return Err("Points cannot be zero.".to_string());
+    }
+
+    if record.prestige_points < points {
+        return Err("Not enough prestige points.".to_string());
+    }
+
+    let Some(mut player) = ctx.db.player().identity().find(ctx.sender) else {
+        return Err("Player not found.".to_string());
+    };
+
+    let gained = points.saturating_mul(PRESTIGE_BONUS_PER_POINT);
+    record.prestige_points -= points;
+    match bonus {
+        PrestigeBonus::ClickPower => {
+            record.click_power_bonus = record.click_power_bonus.saturating_add(gained)
+        }
+        PrestigeBonus::PassiveIncome => {
+            record.passive_income_bonus = record.passive_income_bonus.saturating_add(gained)
+        }
+    }
+    upsert_prestige(ctx, record);
+
+    settle_income(ctx, &mut player);
+    recompute_player_stats(ctx, &mut player);
+    revalue_player(ctx, &mut player);
+    ctx.db.player().identity().update(player);
+    Ok(())
+}
+
+fn prestige_points_for(net_worth: u256) -> u64 {
+    let units = u128::try_from(net_worth / u256::from(PRESTIGE_POINT_DIVISOR)).unwrap_or(u128::MAX);
+    u64::try_from(units.isqrt()).unwrap_or(u64::MAX)
+}
+
+fn upsert_prestige(ctx: &ReducerContext, record: PrestigeRecord) {
+    if ctx.db.prestige_record().identity().find(record.identity).is_some() {
+        ctx.db.prestige_record().identity().update(record);
+    } else {
+        ctx.db.prestige_record().insert(record);
+    }
+}
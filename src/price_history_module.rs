@@ -0,0 +1,122 @@
+use crate::constants::{
+    PRICE_HISTORY_HOUR_MICROS, PRICE_HISTORY_HOUR_RETENTION_MICROS, PRICE_HISTORY_MINUTE_MICROS,
+    PRICE_HISTORY_MINUTE_RETENTION_MICROS, PRICE_HISTORY_TICK_RETENTION_MICROS,
+};
+use spacetimedb::{table, ReducerContext, SpacetimeType, Table};
+
+#[derive(SpacetimeType, PartialEq, Clone, Copy)]
+pub enum CandleResolution {
+    Tick,
+    Minute,
+    Hour,
+}
+
+impl CandleResolution {
+    fn retention_micros(self) -> i64 {
+        match self {
+            CandleResolution::Tick => PRICE_HISTORY_TICK_RETENTION_MICROS,
+            CandleResolution::Minute => PRICE_HISTORY_MINUTE_RETENTION_MICROS,
+            CandleResolution::Hour => PRICE_HISTORY_HOUR_RETENTION_MICROS,
+        }
+    }
+}
+
+#[table(
+    name = stock_price_history,
+    public,
+    index(name = stock_bucket, btree(columns = [stock_id, bucket_start_micros]))
+)]
+pub struct StockPriceHistory {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub stock_id: u16,
+    pub resolution: CandleResolution,
+    pub bucket_start_micros: i64,
+    pub open: u128,
+    pub high: u128,
+    pub low: u128,
+    pub close: u128,
+    pub volume: u64,
+}
+
+/// Records one market tick for a stock: a standalone tick candle, plus folding the tick
+/// into the current minute and hour candles.
+pub fn record_price_history(
+    ctx: &ReducerContext,
+    stock_id: u16,
+    open: u128,
+    close: u128,
+    volume: u64,
+) {
+    let now = ctx.timestamp.to_micros_since_unix_epoch();
+
+    ctx.db.stock_price_history().insert(StockPriceHistory {
+        id: 0,
+        stock_id,
+        resolution: CandleResolution::Tick,
+        bucket_start_micros: now,
+        open,
+        high: open.max(close),
+        low: open.min(close),
+        close,
+        volume,
+    });
+
+    for (resolution, bucket_micros) in [
+        (CandleResolution::Minute, PRICE_HISTORY_MINUTE_MICROS),
+        (CandleResolution::Hour, PRICE_HISTORY_HOUR_MICROS),
+    ] {
+        let bucket_start_micros = now - now.rem_euclid(bucket_micros);
+
+        let existing = ctx
+            .db
+            .stock_price_history()
+            .stock_bucket()
+            .filter((stock_id, bucket_start_micros))
+            .find(|c| c.resolution == resolution);
+
+        match existing {
+            Some(mut candle) => {
+                candle.high = candle.high.max(close);
+                candle.low = candle.low.min(close);
+                candle.close = close;
+                candle.volume += volume;
+                ctx.db.stock_price_history().id().update(candle);
+            }
+            None => {
+                ctx.db.stock_price_history().insert(StockPriceHistory {
+                    id: 0,
+                    stock_id,
+                    resolution,
+                    bucket_start_micros,
+                    open,
+                    high: open.max(close),
+                    low: open.min(close),
+                    close,
+                    volume,
+                });
+
+                if resolution == CandleResolution::Minute {
+                    prune_price_history(ctx, stock_id, now);
+                }
+            }
+        }
+    }
+}
+
+/// Runs once per stock whenever a new minute candle opens, rather than every tick.
+fn prune_price_history(ctx: &ReducerContext, stock_id: u16, now: i64) {
+    let expired: Vec<u64> = ctx
+        .db
+        .stock_price_history()
+        .stock_bucket()
+        .filter(stock_id)
+        .filter(|c| c.bucket_start_micros < now - c.resolution.retention_micros())
+        .map(|c| c.id)
+        .collect();
+
+    for id in expired {
+        ctx.db.stock_price_history().id().delete(id);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::admin_module::ensure_admin;
+use crate::constants::DECIMAL_SCALE_FACTOR;
+use crate::stock_module::{stock, MarketConfig, Stock};
+use spacetimedb::rand::Rng;
+use spacetimedb::{reducer, table, ReducerContext, SpacetimeType, Table};
+
+#[derive(SpacetimeType, PartialEq, Clone, Copy)]
+pub enum PriceModelKind {
+    Demand,
+    RandomWalk,
+    MeanReversion,
+    Hybrid,
+}
+
+/// Per-stock repricing parameters. `drift`, `volatility` and `reversion_speed` are per-tick
+/// rates scaled by `DECIMAL_SCALE_FACTOR`; `fair_value` uses the `price_per_share` scale.
+/// Stocks without a row use the `Demand` model.
+#[table(name = price_model, public)]
+pub struct PriceModel {
+    #[primary_key]
+    pub stock_id: u16,
+    pub kind: PriceModelKind,
+    pub drift: i64,
+    pub volatility: u64,
+    pub fair_value: u128,
+    pub reversion_speed: u64,
+}
+
+#[reducer]
+pub fn set_price_model(
+    ctx: &ReducerContext,
+    stock_id: u16,
+    kind: PriceModelKind,
+    drift: i64,
+    volatility: u64,
+    fair_value: u128,
+    reversion_speed: u64,
+) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    if ctx.db.stock().id().find(stock_id).is_none() {
+        return Err("Stock not found.".to_string());
+    }
+
+    if reversion_speed as u128 > DECIMAL_SCALE_FACTOR {
+        return Err("Reversion speed cannot exceed 100% per tick.".to_string());
+    }
+
+    let model = PriceModel {
+        stock_id,
+        kind,
+        drift,
+        volatility,
+        fair_value,
+        reversion_speed,
+    };
+
+    if ctx.db.price_model().stock_id().find(stock_id).is_some() {
+        ctx.db.price_model().stock_id().update(model);
+    } else {
+        ctx.db.price_model().insert(model);
+    }
+    Ok(())
+}
+
+pub fn next_price(ctx: &ReducerContext, stock: &Stock, config: &MarketConfig) -> u128 {
+    let price = i128::try_from(stock.price_per_share).unwrap_or(i128::MAX);
+
+    let delta = match ctx.db.price_model().stock_id().find(stock.id) {
+        None => demand_delta(stock, config),
+        Some(model) => match model.kind {
+            PriceModelKind::Demand => demand_delta(stock, config),
+            PriceModelKind::RandomWalk => random_walk_delta(ctx, price, &model),
+            PriceModelKind::MeanReversion => {
+                mean_reversion_delta(price, &model).saturating_add(noise_delta(ctx, price, &model))
+            }
+            PriceModelKind::Hybrid => {
+                demand_delta(stock, config)
+                    .saturating_add(mean_reversion_delta(price, &model))
+                    .saturating_add(random_walk_delta(ctx, price, &model))
+            }
+        },
+    };
+
+    bound_price(price.saturating_add(delta), config)
+}
+
+/// Clamps a computed price into the configured `[min_price, max_price]` range.
+pub fn bound_price(price: i128, config: &MarketConfig) -> u128 {
+    (price.max(0) as u128).clamp(config.min_price, config.max_price)
+}
+
+fn demand_delta(stock: &Stock, config: &MarketConfig) -> i128 {
+    let buys = stock.recent_buys as i128;
+    let sells = stock.recent_sells as i128;
+    let price = i128::try_from(stock.price_per_share).unwrap_or(i128::MAX);
+    let sensitivity = config.sensitivity as i128;
+    let slippage = config.slippage_factor as i128;
+    let scale = DECIMAL_SCALE_FACTOR as i128;
+
+    let net_demand = buys - sells;
+    let demand_units = (net_demand * scale) / 100;
+    let delta = price
+        .saturating_mul(demand_units)
+        .saturating_mul(sensitivity)
+        / (scale * scale);
+    delta.saturating_mul(scale - slippage) / scale
+}
+
+/// Geometric Brownian motion step: the move is proportional to the current price.
+fn random_walk_delta(ctx: &ReducerContext, price: i128, model: &PriceModel) -> i128 {
+    let scale = DECIMAL_SCALE_FACTOR as i128;
+    (price.saturating_mul(model.drift as i128) / scale).saturating_add(noise_delta(ctx, price, model))
+}
+
+fn noise_delta(ctx: &ReducerContext, price: i128, model: &PriceModel) -> i128 {
+    let scale = DECIMAL_SCALE_FACTOR as f64;
+    let shock = standard_normal(ctx) * model.volatility as f64 / scale;
+    (price as f64 * shock) as i128
+}
+
+fn mean_reversion_delta(price: i128, model: &PriceModel) -> i128 {
+    let scale = DECIMAL_SCALE_FACTOR as i128;
+    let fair_value = i128::try_from(model.fair_value).unwrap_or(i128::MAX);
+    fair_value.saturating_sub(price).saturating_mul(model.reversion_speed as i128) / scale
+}
+
+/// Box-Muller transform over the reducer's deterministic RNG.
+fn standard_normal(ctx: &ReducerContext) -> f64 {
+    let mut rng = ctx.rng();
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
@@ -0,0 +1,369 @@
+use crate::achievement_module::record_stats;
+use crate::admin_module::ensure_admin;
+use crate::company_module::{ipo_buyback_available, record_ipo_flow};
+use crate::constants::{DECIMAL_SCALE_FACTOR, PRICE_SCALE_FACTOR, SHORT_BORROW_FEE_PERIOD_MICROS};
+use crate::player_module::{player, settle_income, Player};
+use crate::portfolio_module::revalue_player;
+use crate::stock_module::{stock, Stock};
+use crate::transaction_module::{ensure_order_type, OrderType};
+use spacetimedb::sats::u256;
+use spacetimedb::{
+    reducer, table, Identity, ReducerContext, SpacetimeType, Table, TimeDuration, Timestamp,
+};
+
+#[derive(SpacetimeType, PartialEq, Clone, Copy)]
+pub enum ShortStatus {
+    Open,
+    Covered,
+    ForceCovered,
+}
+
+/// Margins are percentages of the position's market value scaled by `DECIMAL_SCALE_FACTOR`.
+/// The borrow fee is charged per `SHORT_BORROW_FEE_PERIOD_MICROS` on the same scale.
+#[table(name = short_config, public)]
+pub struct ShortConfig {
+    pub borrow_fee: u64,
+    pub initial_margin: u64,
+    pub maintenance_margin: u64,
+}
+
+/// Shares borrowed from the pool and sold at `entry_price`. The sale proceeds and the
+/// player's margin deposit are held as `collateral`, which borrow fees are deducted from.
+#[table(name = short_position, public)]
+pub struct ShortPosition {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    #[index(btree)]
+    pub owner: Identity,
+    #[index(btree)]
+    pub stock_id: u16,
+    pub amount: u64,
+    pub entry_price: u128,
+    pub margin_deposit: u128,
+    pub collateral: u128,
+    pub fees_paid: u128,
+    pub status: ShortStatus,
+    pub realized_pnl: i128,
+    pub opened_at: Timestamp,
+    pub last_fee_at: Timestamp,
+    pub closed_at: Option<Timestamp>,
+}
+
+pub fn init_short_config(
+    ctx: &ReducerContext,
+    borrow_fee: u64,
+    initial_margin: u64,
+    maintenance_margin: u64,
+) -> Result<(), String> {
+    if maintenance_margin < DECIMAL_SCALE_FACTOR as u64 || initial_margin < maintenance_margin {
+        return Err(
+            "Initial margin must cover the maintenance margin, which must cover the position."
+                .to_string(),
+        );
+    }
+
+    ctx.db.short_config().insert(ShortConfig {
+        borrow_fee,
+        initial_margin,
+        maintenance_margin,
+    });
+
+    Ok(())
+}
+
+#[reducer]
+pub fn set_short_config(
+    ctx: &ReducerContext,
+    borrow_fee: u64,
+    initial_margin: u64,
+    maintenance_margin: u64,
+) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    for config in ctx.db.short_config().iter() {
+        ctx.db.short_config().delete(config);
+    }
+
+    init_short_config(ctx, borrow_fee, initial_margin, maintenance_margin)
+}
+
+/// Borrows `amount` shares from the pool and sells them at the current price. The player
+/// deposits enough money, plus the sell fee, to bring collateral up to the initial margin.
+#[reducer]
+pub fn open_short(ctx: &ReducerContext, stock_id: u16, amount: u64) -> Result<(), String> {
+    let config = short_config(ctx)?;
+
+    let Some(mut player) = ctx.db.player().identity().find(ctx.sender) else {
+        return Err("Player not found.".to_string());
+    };
+
+    ensure_order_type(&player, OrderType::ShortSell)?;
+
+    let Some(mut stock) = ctx.db.stock().id().find(stock_id) else {
+        return Err("Stock not found.".to_string());
+    };
+
+    if amount == 0 {
+        return Err("Amount cannot be zero.".to_string());
+    }
+
+    if amount > player.max_order_shares {
+        return Err("Order exceeds your per-order share limit.".to_string());
+    }
+
+    if stock.available_shares < amount {
+        return Err("Not enough shares available to borrow.".to_string());
+    }
+
+    let value = stock
+        .price_per_share
+        .checked_mul(amount.into())
+        .ok_or("Position is too large.".to_string())?;
+    let fee = (value * player.stock_sell_fee as u128) / PRICE_SCALE_FACTOR;
+    let collateral = scale_margin(value, config.initial_margin);
+    let margin_deposit = collateral - value + fee;
+
+    if !ipo_buyback_available(ctx, stock_id, value) {
+        return Err("The house cannot buy back more than was paid into this stock.".to_string());
+    }
+
+    settle_income(ctx, &mut player);
+    if player.money < u256::from(margin_deposit) {
+        return Err("Not enough money for the required collateral.".to_string());
+    }
+
+    player.money -= u256::from(margin_deposit);
+    record_ipo_flow(ctx, stock_id, 0, value);
+    stock.available_shares -= amount;
+    stock.recent_sells += amount;
+
+    ctx.db.short_position().insert(ShortPosition {
+        id: 0,
+        owner: ctx.sender,
+        stock_id,
+        amount,
+        entry_price: stock.price_per_share,
+        margin_deposit,
+        collateral,
+        fees_paid: 0,
+        status: ShortStatus::Open,
+        realized_pnl: 0,
+        opened_at: ctx.timestamp,
+        last_fee_at: ctx.timestamp,
+        closed_at: None,
+    });
+
+    ctx.db.stock().id().update(stock);
+    record_stats(ctx, &mut player, |stats| stats.trades_executed += 1);
+    revalue_player(ctx, &mut player);
+    ctx.db.player().identity().update(player);
+    Ok(())
+}
+
+/// Buys the borrowed shares back at the current price and returns them to the pool,
+/// releasing the remaining collateral to the player.
+#[reducer]
+pub fn cover_short(ctx: &ReducerContext, position_id: u64) -> Result<(), String> {
+    let Some(mut position) = ctx.db.short_position().id().find(position_id) else {
+        return Err("Short position not found.".to_string());
+    };
+
+    if position.owner != ctx.sender {
+        return Err("Only the owner can cover this position.".to_string());
+    }
+
+    if position.status != ShortStatus::Open {
+        return Err("Short position is already closed.".to_string());
+    }
+
+    let Some(mut player) = ctx.db.player().identity().find(ctx.sender) else {
+        return Err("Player not found.".to_string());
+    };
+
+    let Some(mut stock) = ctx.db.stock().id().find(position.stock_id) else {
+        return Err("Stock not found.".to_string());
+    };
+
+    settle_income(ctx, &mut player);
+    close_short(
+        ctx,
+        &mut player,
+        &mut position,
+        &mut stock,
+        ShortStatus::Covered,
+    )?;
+
+    ctx.db.short_position().id().update(position);
+    ctx.db.stock().id().update(stock);
+    revalue_player(ctx, &mut player);
+    ctx.db.player().identity().update(player);
+    Ok(())
+}
+
+/// Charges the borrow fee for every whole fee period since the last charge against each open
+/// position's collateral and force-covers positions whose collateral has fallen below the
+/// maintenance margin. Runs after repricing in `update_stock_prices`.
+pub fn settle_short_positions(ctx: &ReducerContext) {
+    let Ok(config) = short_config(ctx) else {
+        return;
+    };
+
+    for mut position in ctx
+        .db
+        .short_position()
+        .iter()
+        .filter(|p| p.status == ShortStatus::Open)
+    {
+        let Some(mut stock) = ctx.db.stock().id().find(position.stock_id) else {
+            continue;
+        };
+
+        let value = u256::from(stock.price_per_share) * u256::from(position.amount);
+        // Only whole periods are billed, and the clock advances by exactly the time billed,
+        // so short ticks accumulate instead of truncating the fee to zero.
+        let periods = ctx
+            .timestamp
+            .time_duration_since(position.last_fee_at)
+            .map_or(0, |d| {
+                d.to_micros().max(0) as u64 / SHORT_BORROW_FEE_PERIOD_MICROS
+            });
+        if periods > 0 {
+            let fee = value * u256::from(config.borrow_fee) * u256::from(periods)
+                / u256::from(DECIMAL_SCALE_FACTOR);
+            let fee = u128::try_from(fee)
+                .unwrap_or(u128::MAX)
+                .min(position.collateral);
+            position.collateral -= fee;
+            position.fees_paid += fee;
+            position.last_fee_at +=
+                TimeDuration::from_micros((periods * SHORT_BORROW_FEE_PERIOD_MICROS) as i64);
+        }
+
+        let maintenance =
+            value * u256::from(config.maintenance_margin) / u256::from(DECIMAL_SCALE_FACTOR);
+        if u256::from(position.collateral) < maintenance {
+            if let Some(mut player) = ctx.db.player().identity().find(position.owner) {
+                settle_income(ctx, &mut player);
+                match close_short(
+                    ctx,
+                    &mut player,
+                    &mut position,
+                    &mut stock,
+                    ShortStatus::ForceCovered,
+                ) {
+                    Ok(()) => {
+                        log::info!("Force-covered short position {}", position.id);
+                        ctx.db.stock().id().update(stock);
+                    }
+                    Err(reason) => {
+                        log::error!("Could not force-cover short {}: {}", position.id, reason)
+                    }
+                }
+                revalue_player(ctx, &mut player);
+                ctx.db.player().identity().update(player);
+            }
+        }
+
+        ctx.db.short_position().id().update(position);
+    }
+}
+
+/// Closes every open short of a player without buying back, returning the borrowed shares
+/// to the pool. Used when a prestige reset discards the player's money anyway.
+pub fn release_short_positions(ctx: &ReducerContext, owner: Identity) {
+    for mut position in ctx
+        .db
+        .short_position()
+        .owner()
+        .filter(owner)
+        .filter(|p| p.status == ShortStatus::Open)
+    {
+        if let Some(mut stock) = ctx.db.stock().id().find(position.stock_id) {
+            stock.available_shares += position.amount;
+            ctx.db.stock().id().update(stock);
+        }
+
+        position.status = ShortStatus::ForceCovered;
+        position.closed_at = Some(ctx.timestamp);
+        ctx.db.short_position().id().update(position);
+    }
+}
+
+/// Open shorts as `(collateral, liability)`, with the liability marked to `price_of`.
+pub fn short_exposure(
+    ctx: &ReducerContext,
+    owner: Identity,
+    price_of: impl Fn(u16) -> u128,
+) -> (u256, u256) {
+    ctx.db
+        .short_position()
+        .owner()
+        .filter(owner)
+        .filter(|p| p.status == ShortStatus::Open)
+        .fold(
+            (u256::new(0), u256::new(0)),
+            |(collateral, liability), p| {
+                (
+                    collateral + u256::from(p.collateral),
+                    liability + u256::from(price_of(p.stock_id)) * u256::from(p.amount),
+                )
+            },
+        )
+}
+
+/// Buy-back cost is paid from collateral first. A voluntary cover fails if the player cannot
+/// pay the rest; a forced cover takes what money the player has and writes off the remainder.
+fn close_short(
+    ctx: &ReducerContext,
+    player: &mut Player,
+    position: &mut ShortPosition,
+    stock: &mut Stock,
+    status: ShortStatus,
+) -> Result<(), String> {
+    let price = u256::from(stock.price_per_share) * u256::from(position.amount);
+    let fee = price * u256::from(player.stock_buy_fee) / u256::from(PRICE_SCALE_FACTOR);
+    let cost = price + fee;
+    let available = player.money + u256::from(position.collateral);
+
+    if available < cost && status == ShortStatus::Covered {
+        return Err("Not enough money to cover the position.".to_string());
+    }
+
+    player.money = available.saturating_sub(cost);
+    record_ipo_flow(ctx, stock.id, u128::try_from(price).unwrap_or(u128::MAX), 0);
+    let pnl =
+        to_i128(u256::from(position.collateral)) - to_i128(cost) - position.margin_deposit as i128;
+
+    stock.available_shares += position.amount;
+    stock.recent_buys += position.amount;
+
+    position.collateral = 0;
+    position.realized_pnl = pnl;
+    position.status = status;
+    position.closed_at = Some(ctx.timestamp);
+
+    player.realized_pnl += pnl;
+    record_stats(ctx, player, |stats| {
+        stats.trades_executed += 1;
+        stats.biggest_single_profit = stats.biggest_single_profit.max(pnl);
+    });
+    Ok(())
+}
+
+fn short_config(ctx: &ReducerContext) -> Result<ShortConfig, String> {
+    ctx.db
+        .short_config()
+        .iter()
+        .next()
+        .ok_or("Short selling configuration not initialized.".to_string())
+}
+
+fn scale_margin(value: u128, margin: u64) -> u128 {
+    u128::try_from(u256::from(value) * u256::from(margin) / u256::from(DECIMAL_SCALE_FACTOR))
+        .unwrap_or(u128::MAX)
+}
+
+fn to_i128(value: u256) -> i128 {
+    i128::try_from(value).unwrap_or(i128::MAX)
+}
@@ -1,6 +1,14 @@
+use crate::admin_module::ensure_admin;
+use crate::company_module::{list_ipos, unlock_founder_shares};
 use crate::constants::PRICE_SCALE_FACTOR;
-use crate::constants::{DECIMAL_SCALE_FACTOR, STOCK_UPDATE_INTERVAL_MICROS};
-use crate::transaction_module::update_transactions;
+use crate::constants::STOCK_UPDATE_INTERVAL_MICROS;
+use crate::margin_module::settle_margin_accounts;
+use crate::market_event_module::apply_event_drift;
+use crate::portfolio_module::revalue_portfolios;
+use crate::price_history_module::record_price_history;
+use crate::price_model_module::next_price;
+use crate::short_module::settle_short_positions;
+use crate::transaction_module::{evaluate_protective_orders, update_transactions};
 use spacetimedb::{reducer, table, ReducerContext, ScheduleAt, Table};
 use std::time::Duration;
 
@@ -33,6 +41,7 @@ pub struct MarketConfig {
     pub sensitivity: u64,
     pub slippage_factor: u64,
     pub min_price: u128,
+    pub max_price: u128,
 }
 
 pub fn init_market_config(
@@ -40,11 +49,17 @@ pub fn init_market_config(
     sensitivity_scaled: u64,
     slippage_factor_scaled: u64,
     min_price_scaled: u128,
+    max_price_scaled: u128,
 ) -> Result<(), String> {
+    if max_price_scaled < min_price_scaled {
+        return Err("Max price cannot be below the min price.".to_string());
+    }
+
     ctx.db.market_config().insert(MarketConfig {
         sensitivity: sensitivity_scaled,
         slippage_factor: slippage_factor_scaled,
         min_price: min_price_scaled,
+        max_price: max_price_scaled,
     });
 
     Ok(())
@@ -59,6 +74,8 @@ pub fn create_stock(
     initial_price: u128,
     total_shares: u64,
 ) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
     if total_shares == 0 {
         return Err("Total shares cannot be zero.".to_string());
     }
@@ -83,6 +100,8 @@ pub fn create_stock(
 
 #[reducer]
 pub fn update_stock_prices(ctx: &ReducerContext, _args: StockMarketSchedule) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
     let config = ctx
         .db
         .market_config()
@@ -90,29 +109,28 @@ pub fn update_stock_prices(ctx: &ReducerContext, _args: StockMarketSchedule) ->
         .next()
         .ok_or("Market configuration not initialized.")?;
 
+    list_ipos(ctx, &config);
+
     if let Err(e) = update_transactions(ctx) {
         log::error!("Could not process transactions during market update: {}", e);
     }
+    unlock_founder_shares(ctx);
 
+    let mut repriced = Vec::new();
     for mut stock in ctx.db.stock().iter() {
-        let buys = stock.recent_buys as i128;
-        let sells = stock.recent_sells as i128;
-        if buys == 0 && sells == 0 {
+        let open = stock.price_per_share;
+        let volume = stock.recent_buys + stock.recent_sells;
+        let new_price = apply_event_drift(ctx, stock.id, next_price(ctx, &stock, &config));
+
+        record_price_history(ctx, stock.id, open, new_price, volume);
+        if volume == 0 && new_price == open {
             continue;
         }
-        let price = stock.price_per_share as i128;
-        let sensitivity = config.sensitivity as i128;
-        let slippage = config.slippage_factor as i128;
-        let scale = DECIMAL_SCALE_FACTOR as i128;
-
-        let net_demand = buys - sells;
-        let demand_units = (net_demand * scale) / 100;
-        let delta = (price * demand_units * sensitivity) / (scale * scale);
-        let adjusted_delta = (delta * (scale - slippage)) / scale;
-
-        let new_price = (price + adjusted_delta).max(config.min_price as i128) as u128;
 
-        stock.last_price = stock.price_per_share;
+        if new_price != open {
+            repriced.push(stock.id);
+        }
+        stock.last_price = open;
         stock.price_per_share = new_price;
         stock.recent_buys = 0;
         stock.recent_sells = 0;
@@ -120,6 +138,11 @@ pub fn update_stock_prices(ctx: &ReducerContext, _args: StockMarketSchedule) ->
         ctx.db.stock().id().update(stock);
     }
 
+    settle_short_positions(ctx);
+    settle_margin_accounts(ctx);
+    evaluate_protective_orders(ctx);
+    revalue_portfolios(ctx, &repriced);
+
     ctx.db.stock_market_schedule().insert(StockMarketSchedule {
         id: 0,
         scheduled_at: (ctx.timestamp + Duration::from_micros(STOCK_UPDATE_INTERVAL_MICROS)).into(),
@@ -1,6 +1,13 @@
+use crate::achievement_module::record_stats;
+use crate::admin_module::ensure_admin;
+use crate::company_module::{ipo_buyback_available, record_ipo_flow};
 use crate::constants::PRICE_SCALE_FACTOR;
-use crate::player_module::{player, StockType};
-use crate::stock_module::stock;
+use crate::margin_module::draw_margin;
+use crate::order_book_module::{match_order_book, record_trade};
+use crate::player_module::{player, settle_income, Player, StockType};
+use crate::portfolio_module::revalue_player;
+use crate::stock_module::{market_config, stock, Stock};
+use spacetimedb::sats::u256;
 use spacetimedb::{reducer, table, Identity, ReducerContext, SpacetimeType, Table, Timestamp};
 
 #[derive(SpacetimeType, PartialEq, Clone)]
@@ -8,6 +15,8 @@ pub enum TransactionStatus {
     Pending,
     Confirmed,
     Rejected,
+    Expired,
+    Cancelled,
 }
 
 #[derive(SpacetimeType, PartialEq, Clone)]
@@ -16,18 +25,71 @@ pub enum TransactionType {
     Sell,
 }
 
+#[derive(SpacetimeType, PartialEq, Clone, Copy)]
+pub enum OrderType {
+    Market,
+    Limit,
+    StopLoss,
+    TakeProfit,
+    ShortSell,
+    Margin,
+}
+
+/// Order types every player can use without an upgrade. Upgrades only unlock types beyond these,
+/// which gate `open_short` and `set_margin_leverage`.
+pub const DEFAULT_ORDER_TYPES: [OrderType; 4] = [
+    OrderType::Market,
+    OrderType::Limit,
+    OrderType::StopLoss,
+    OrderType::TakeProfit,
+];
+
+#[derive(SpacetimeType, PartialEq, Clone)]
+pub enum ProtectiveOrderType {
+    StopLoss,
+    TakeProfit,
+}
+
+#[derive(SpacetimeType, PartialEq, Clone)]
+pub enum ProtectiveOrderStatus {
+    Active,
+    Triggered,
+    Cancelled,
+}
+
 #[table(name = transaction, public)]
 pub struct Transaction {
     #[unique]
     #[auto_inc]
     #[primary_key]
-    pub id: u16,
+    pub id: u64,
     pub sender: Identity,
+    #[index(btree)]
     pub stock_id: u16,
     pub amount: u64,
+    pub filled_amount: u64,
     pub tx_type: TransactionType,
     pub status: TransactionStatus,
     pub timestamp: Timestamp,
+    pub limit_price: Option<u128>,
+    pub expires_at: Option<Timestamp>,
+    pub triggered_by: Option<u64>,
+}
+
+#[table(name = protective_order, public)]
+pub struct ProtectiveOrder {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    #[index(btree)]
+    pub owner: Identity,
+    pub stock_id: u16,
+    pub amount: u64,
+    pub order_type: ProtectiveOrderType,
+    pub trigger_price: u128,
+    pub status: ProtectiveOrderStatus,
+    pub created_at: Timestamp,
+    pub triggered_at: Option<Timestamp>,
 }
 
 #[reducer]
@@ -37,7 +99,7 @@ pub fn create_transaction(
     amount: u64,
     tx_type: TransactionType,
 ) -> Result<(), String> {
-    let Some(_player) = ctx.db.player().identity().find(ctx.sender) else {
+    let Some(player) = ctx.db.player().identity().find(ctx.sender) else {
         return Err("Player not found.".to_string());
     };
 
@@ -45,21 +107,331 @@ pub fn create_transaction(
         return Err("Stock not found.".to_string());
     };
 
+    if amount == 0 {
+        return Err("Amount cannot be zero.".to_string());
+    }
+
+    check_order_access(&player, OrderType::Market, amount)?;
+
     ctx.db.transaction().insert(Transaction {
         id: 0,
         sender: ctx.sender,
         stock_id,
         amount,
+        filled_amount: 0,
         tx_type,
         status: TransactionStatus::Pending,
         timestamp: ctx.timestamp,
+        limit_price: None,
+        expires_at: None,
+        triggered_by: None,
+    });
+
+    Ok(())
+}
+
+/// Limit price uses the same scale as `Stock.price_per_share`. The order stays pending
+/// across market ticks until the price crosses the limit or the order expires.
+#[reducer]
+pub fn create_limit_order(
+    ctx: &ReducerContext,
+    stock_id: u16,
+    amount: u64,
+    tx_type: TransactionType,
+    limit_price: u128,
+    expires_at: Option<Timestamp>,
+) -> Result<(), String> {
+    let Some(player) = ctx.db.player().identity().find(ctx.sender) else {
+        return Err("Player not found.".to_string());
+    };
+
+    let Some(_stock) = ctx.db.stock().id().find(stock_id) else {
+        return Err("Stock not found.".to_string());
+    };
+
+    if amount == 0 {
+        return Err("Amount cannot be zero.".to_string());
+    }
+
+    check_order_access(&player, OrderType::Limit, amount)?;
+    check_limit_price(ctx, limit_price)?;
+
+    if let Some(expiry) = expires_at {
+        if expiry <= ctx.timestamp {
+            return Err("Expiry must be in the future.".to_string());
+        }
+    }
+
+    ctx.db.transaction().insert(Transaction {
+        id: 0,
+        sender: ctx.sender,
+        stock_id,
+        amount,
+        filled_amount: 0,
+        tx_type,
+        status: TransactionStatus::Pending,
+        timestamp: ctx.timestamp,
+        limit_price: Some(limit_price),
+        expires_at,
+        triggered_by: None,
+    });
+
+    Ok(())
+}
+
+#[reducer]
+pub fn cancel_transaction(ctx: &ReducerContext, transaction_id: u64) -> Result<(), String> {
+    let mut tx = find_own_pending_transaction(ctx, transaction_id)?;
+
+    tx.status = TransactionStatus::Cancelled;
+    ctx.db.transaction().id().update(tx);
+    Ok(())
+}
+
+/// Replaces the amount and limit price of a pending order. Market orders stay market orders.
+/// The order keeps its queue position only when the amount shrinks and the price is unchanged.
+#[reducer]
+pub fn amend_transaction(
+    ctx: &ReducerContext,
+    transaction_id: u64,
+    amount: u64,
+    limit_price: Option<u128>,
+) -> Result<(), String> {
+    let mut tx = find_own_pending_transaction(ctx, transaction_id)?;
+
+    if amount == 0 {
+        return Err("Amount cannot be zero.".to_string());
+    }
+
+    let Some(player) = ctx.db.player().identity().find(ctx.sender) else {
+        return Err("Player not found.".to_string());
+    };
+    if amount > player.max_order_shares {
+        return Err("Order exceeds your per-order share limit.".to_string());
+    }
+
+    if amount <= tx.filled_amount {
+        return Err("Amount must exceed the already filled amount.".to_string());
+    }
+
+    match (tx.limit_price, limit_price) {
+        (None, Some(_)) => return Err("Cannot add a limit price to a market order.".to_string()),
+        (Some(_), None) => return Err("Limit orders require a limit price.".to_string()),
+        (_, Some(limit)) => check_limit_price(ctx, limit)?,
+        _ => {}
+    }
+
+    // Growing an order or moving its price sends it to the back of the queue.
+    if amount > tx.amount || limit_price != tx.limit_price {
+        tx.timestamp = ctx.timestamp;
+    }
+    tx.amount = amount;
+    tx.limit_price = limit_price;
+    ctx.db.transaction().id().update(tx);
+    Ok(())
+}
+
+pub fn remaining_amount(tx: &Transaction) -> u64 {
+    tx.amount.saturating_sub(tx.filled_amount)
+}
+
+pub fn ensure_order_type(player: &Player, order_type: OrderType) -> Result<(), String> {
+    if !DEFAULT_ORDER_TYPES.contains(&order_type)
+        && !player.unlocked_order_types.contains(&order_type)
+    {
+        return Err("Order type not unlocked.".to_string());
+    }
+
+    Ok(())
+}
+
+fn check_order_access(player: &Player, order_type: OrderType, amount: u64) -> Result<(), String> {
+    ensure_order_type(player, order_type)?;
+
+    if amount > player.max_order_shares {
+        return Err("Order exceeds your per-order share limit.".to_string());
+    }
+
+    Ok(())
+}
+
+/// Limit prices must lie within the range the market can reach.
+fn check_limit_price(ctx: &ReducerContext, limit_price: u128) -> Result<(), String> {
+    if limit_price == 0 {
+        return Err("Limit price cannot be zero.".to_string());
+    }
+
+    let max_price = ctx
+        .db
+        .market_config()
+        .iter()
+        .next()
+        .ok_or("Market configuration not initialized.".to_string())?
+        .max_price;
+    if limit_price > max_price {
+        return Err("Limit price exceeds the maximum share price.".to_string());
+    }
+
+    Ok(())
+}
+
+fn find_own_pending_transaction(
+    ctx: &ReducerContext,
+    transaction_id: u64,
+) -> Result<Transaction, String> {
+    let Some(tx) = ctx.db.transaction().id().find(transaction_id) else {
+        return Err("Transaction not found.".to_string());
+    };
+
+    if tx.sender != ctx.sender {
+        return Err("Only the sender can modify this transaction.".to_string());
+    }
+
+    if tx.status != TransactionStatus::Pending {
+        return Err("Transaction is no longer pending.".to_string());
+    }
+
+    Ok(tx)
+}
+
+/// Trigger price uses the same scale as `Stock.price_per_share`. Stop-loss fires when the
+/// price falls to the trigger, take-profit when it rises to it.
+#[reducer]
+pub fn create_protective_order(
+    ctx: &ReducerContext,
+    stock_id: u16,
+    amount: u64,
+    order_type: ProtectiveOrderType,
+    trigger_price: u128,
+) -> Result<(), String> {
+    let Some(player) = ctx.db.player().identity().find(ctx.sender) else {
+        return Err("Player not found.".to_string());
+    };
+
+    let Some(_stock) = ctx.db.stock().id().find(stock_id) else {
+        return Err("Stock not found.".to_string());
+    };
+
+    if amount == 0 {
+        return Err("Amount cannot be zero.".to_string());
+    }
+
+    let required = match order_type {
+        ProtectiveOrderType::StopLoss => OrderType::StopLoss,
+        ProtectiveOrderType::TakeProfit => OrderType::TakeProfit,
+    };
+    check_order_access(&player, required, amount)?;
+
+    if trigger_price == 0 {
+        return Err("Trigger price cannot be zero.".to_string());
+    }
+
+    let held = player
+        .stocks
+        .iter()
+        .find(|s| s.stock_id == stock_id)
+        .map_or(0, |s| s.amount);
+    if held < amount {
+        return Err("Not enough shares to protect.".to_string());
+    }
+
+    ctx.db.protective_order().insert(ProtectiveOrder {
+        id: 0,
+        owner: ctx.sender,
+        stock_id,
+        amount,
+        order_type,
+        trigger_price,
+        status: ProtectiveOrderStatus::Active,
+        created_at: ctx.timestamp,
+        triggered_at: None,
     });
 
     Ok(())
 }
 
+#[reducer]
+pub fn cancel_protective_order(ctx: &ReducerContext, order_id: u64) -> Result<(), String> {
+    let Some(mut order) = ctx.db.protective_order().id().find(order_id) else {
+        return Err("Order not found.".to_string());
+    };
+
+    if order.owner != ctx.sender {
+        return Err("Only the owner can cancel this order.".to_string());
+    }
+
+    if order.status != ProtectiveOrderStatus::Active {
+        return Err("Order is no longer active.".to_string());
+    }
+
+    order.status = ProtectiveOrderStatus::Cancelled;
+    ctx.db.protective_order().id().update(order);
+    Ok(())
+}
+
+/// Converts every active protective order whose threshold has been crossed into a
+/// pending market sell, to be settled on the next call to `update_transactions`.
+pub fn evaluate_protective_orders(ctx: &ReducerContext) {
+    for mut order in ctx
+        .db
+        .protective_order()
+        .iter()
+        .filter(|o| o.status == ProtectiveOrderStatus::Active)
+    {
+        let Some(stock) = ctx.db.stock().id().find(order.stock_id) else {
+            order.status = ProtectiveOrderStatus::Cancelled;
+            ctx.db.protective_order().id().update(order);
+            continue;
+        };
+
+        let triggered = match order.order_type {
+            ProtectiveOrderType::StopLoss => stock.price_per_share <= order.trigger_price,
+            ProtectiveOrderType::TakeProfit => stock.price_per_share >= order.trigger_price,
+        };
+        if !triggered {
+            continue;
+        }
+
+        ctx.db.transaction().insert(Transaction {
+            id: 0,
+            sender: order.owner,
+            stock_id: order.stock_id,
+            amount: order.amount,
+            filled_amount: 0,
+            tx_type: TransactionType::Sell,
+            status: TransactionStatus::Pending,
+            timestamp: ctx.timestamp,
+            limit_price: None,
+            expires_at: None,
+            triggered_by: Some(order.id),
+        });
+
+        order.status = ProtectiveOrderStatus::Triggered;
+        order.triggered_at = Some(ctx.timestamp);
+        ctx.db.protective_order().id().update(order);
+    }
+}
+
 #[reducer]
 pub fn update_transactions(ctx: &ReducerContext) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    for mut tx in ctx
+        .db
+        .transaction()
+        .iter()
+        .filter(|t| t.status == TransactionStatus::Pending)
+        .filter(|t| t.expires_at.is_some_and(|expiry| ctx.timestamp >= expiry))
+    {
+        tx.status = TransactionStatus::Expired;
+        ctx.db.transaction().id().update(tx);
+    }
+
+    for stock in ctx.db.stock().iter() {
+        match_order_book(ctx, stock);
+    }
+
+    // Whatever the book could not match is filled against the house pool.
     for tx in ctx
         .db
         .transaction()
@@ -80,75 +452,181 @@ pub fn update_transactions(ctx: &ReducerContext) -> Result<(), String> {
             continue;
         };
 
-        let Some(total_price) = stock.price_per_share.checked_mul(tx.amount.into()) else {
+        if let Some(limit) = tx.limit_price {
+            let triggered = match tx.tx_type {
+                TransactionType::Buy => stock.price_per_share <= limit,
+                TransactionType::Sell => stock.price_per_share >= limit,
+            };
+            if !triggered {
+                continue;
+            }
+        }
+
+        let amount = remaining_amount(&tx);
+        let Some(total_price) = stock.price_per_share.checked_mul(amount.into()) else {
             tx.status = TransactionStatus::Rejected;
             ctx.db.transaction().id().update(tx);
             continue;
         };
 
-        match tx.tx_type {
-            TransactionType::Buy => {
-                let fee = (total_price * player.stock_buy_fee as u128) / PRICE_SCALE_FACTOR;
-                let total_cost = total_price + fee;
-
-                if player.money < total_cost {
-                    tx.status = TransactionStatus::Rejected;
-                    ctx.db.transaction().id().update(tx);
-                    continue;
-                }
-                
-                if stock.available_shares < tx.amount {
-                    tx.status = TransactionStatus::Rejected;
-                    ctx.db.transaction().id().update(tx);
-                    continue;
-                }
+        if tx.amount > player.max_order_shares {
+            tx.status = TransactionStatus::Rejected;
+            ctx.db.transaction().id().update(tx);
+            continue;
+        }
 
-                player.money -= total_cost;
-                stock.available_shares -= tx.amount;
-                stock.recent_buys += tx.amount;
+        settle_income(ctx, &mut player);
 
-                match player.stocks.iter_mut().find(|s| s.stock_id == tx.stock_id) {
-                    Some(existing) => existing.amount += tx.amount,
-                    None => player.stocks.push(StockType {
-                        stock_id: tx.stock_id,
-                        amount: tx.amount,
-                    }),
+        match apply_trade(ctx, &mut player, &mut stock, &tx, amount, total_price) {
+            Ok(pnl) => {
+                match tx.tx_type {
+                    TransactionType::Buy => {
+                        record_trade(ctx, stock.id, Some(&tx), None, amount, stock.price_per_share)
+                    }
+                    TransactionType::Sell => {
+                        record_trade(ctx, stock.id, None, Some(&tx), amount, stock.price_per_share)
+                    }
+                }
+                if amount > 0 {
+                    record_stats(ctx, &mut player, |stats| {
+                        stats.trades_executed += 1;
+                        if let Some(pnl) = pnl {
+                            stats.biggest_single_profit = stats.biggest_single_profit.max(pnl);
+                        }
+                    });
                 }
+                ctx.db.stock().id().update(stock);
+                revalue_player(ctx, &mut player);
+                tx.filled_amount = tx.amount;
+                tx.status = TransactionStatus::Confirmed;
             }
+            Err(reason) => {
+                log::debug!("Rejected transaction {}: {}", tx.id, reason);
+                tx.status = TransactionStatus::Rejected;
+            }
+        }
 
-            TransactionType::Sell => {
-                let Some(existing) = player.stocks.iter_mut().find(|s| s.stock_id == tx.stock_id)
-                else {
-                    tx.status = TransactionStatus::Rejected;
-                    ctx.db.transaction().id().update(tx);
-                    continue;
-                };
-
-                if existing.amount < tx.amount {
-                    tx.status = TransactionStatus::Rejected;
-                    ctx.db.transaction().id().update(tx);
-                    continue;
-                }
+        ctx.db.player().identity().update(player);
+        ctx.db.transaction().id().update(tx);
+    }
 
-                let fee = (total_price * player.stock_sell_fee as u128) / PRICE_SCALE_FACTOR;
-                let proceeds = total_price.saturating_sub(fee);
+    Ok(())
+}
 
-                existing.amount -= tx.amount;
-                player.money += proceeds;
-                stock.available_shares += tx.amount;
-                stock.recent_sells += tx.amount;
+/// Fills the remainder of an order against the house pool. Returns the realized P&L for sells.
+fn apply_trade(
+    ctx: &ReducerContext,
+    player: &mut Player,
+    stock: &mut Stock,
+    tx: &Transaction,
+    amount: u64,
+    total_price: u128,
+) -> Result<Option<i128>, String> {
+    match tx.tx_type {
+        TransactionType::Buy => {
+            if stock.available_shares < amount {
+                return Err("Not enough shares available.".to_string());
+            }
 
-                if existing.amount == 0 {
-                    player.stocks.retain(|s| s.stock_id != tx.stock_id);
-                }
+            buy_shares(ctx, player, tx.stock_id, amount, total_price)?;
+            record_ipo_flow(ctx, tx.stock_id, total_price, 0);
+            stock.available_shares -= amount;
+            stock.recent_buys += amount;
+            Ok(None)
+        }
+
+        TransactionType::Sell => {
+            if !ipo_buyback_available(ctx, tx.stock_id, total_price) {
+                return Err(
+                    "The house cannot buy back more than was paid into this stock.".to_string(),
+                );
             }
+
+            let pnl = sell_shares(player, tx.stock_id, amount, total_price)?;
+            record_ipo_flow(ctx, tx.stock_id, 0, total_price);
+            stock.available_shares += amount;
+            stock.recent_sells += amount;
+            Ok(Some(pnl))
         }
+    }
+}
 
-        ctx.db.player().identity().update(player);
-        ctx.db.stock().id().update(stock);
-        tx.status = TransactionStatus::Confirmed;
-        ctx.db.transaction().id().update(tx);
+/// Charges the buy fee and adds the shares to the player's position. A shortfall in cash is
+/// borrowed from the player's margin account when it has room and the fill is not above the
+/// current share price.
+pub fn buy_shares(
+    ctx: &ReducerContext,
+    player: &mut Player,
+    stock_id: u16,
+    amount: u64,
+    total_price: u128,
+) -> Result<(), String> {
+    let fee = (total_price * player.stock_buy_fee as u128) / PRICE_SCALE_FACTOR;
+    let total_cost = total_price + fee;
+
+    if player.money < u256::from(total_cost) {
+        let market_value = ctx
+            .db
+            .stock()
+            .id()
+            .find(stock_id)
+            .map_or(u256::new(0), |s| u256::from(s.price_per_share) * u256::from(amount));
+        if u256::from(total_price) > market_value {
+            return Err("Margin cannot pay above the current share price.".to_string());
+        }
+        draw_margin(ctx, player, u256::from(total_cost) - player.money)?;
     }
 
+    player.money -= total_cost;
+
+    match player.stocks.iter_mut().find(|s| s.stock_id == stock_id) {
+        Some(existing) => {
+            existing.amount += amount;
+            existing.cost_basis += total_cost;
+        }
+        None => player.stocks.push(StockType {
+            stock_id,
+            amount,
+            cost_basis: total_cost,
+            realized_pnl: 0,
+            unrealized_pnl: 0,
+        }),
+    }
     Ok(())
 }
+
+/// Removes the shares from the player's position and credits the proceeds net of the sell
+/// fee. Returns the realized P&L against the proportional cost basis.
+pub fn sell_shares(
+    player: &mut Player,
+    stock_id: u16,
+    amount: u64,
+    total_price: u128,
+) -> Result<i128, String> {
+    let Some(existing) = player.stocks.iter_mut().find(|s| s.stock_id == stock_id) else {
+        return Err("No shares held.".to_string());
+    };
+
+    if existing.amount < amount {
+        return Err("Not enough shares held.".to_string());
+    }
+
+    let fee = (total_price * player.stock_sell_fee as u128) / PRICE_SCALE_FACTOR;
+    let proceeds = total_price.saturating_sub(fee);
+    let sold_basis = u128::try_from(
+        u256::from(existing.cost_basis) * u256::from(amount) / u256::from(existing.amount),
+    )
+    .unwrap_or(existing.cost_basis);
+    let pnl = proceeds as i128 - sold_basis as i128;
+
+    existing.cost_basis -= sold_basis;
+    existing.realized_pnl += pnl;
+    player.realized_pnl += pnl;
+    existing.amount -= amount;
+    player.money += proceeds;
+
+    if existing.amount == 0 {
+        player.stocks.retain(|s| s.stock_id != stock_id);
+    }
+    Ok(pnl)
+}
@@ -0,0 +1,264 @@
+use crate::admin_module::ensure_admin;
+use crate::constants::{PRICE_SCALE_FACTOR, TRANSFER_LIMIT_WINDOW_MICROS};
+use crate::margin_module::margin_debt;
+use crate::player_module::{player, settle_income, StockType};
+use crate::portfolio_module::revalue_player;
+use crate::stock_module::stock;
+use spacetimedb::sats::u256;
+use spacetimedb::{reducer, table, Identity, ReducerContext, SpacetimeType, Table, TimeDuration, Timestamp};
+
+#[derive(SpacetimeType, PartialEq, Clone, Copy)]
+pub enum TransferKind {
+    Money,
+    Shares(u16),
+}
+
+/// Fees use the same scale as `Player.stock_buy_fee`; the share fee is charged in money
+/// against the market value of the shares. Limits apply over a rolling 24 hours.
+#[table(name = transfer_config, public)]
+pub struct TransferConfig {
+    pub money_fee: u16,
+    pub share_fee: u16,
+    pub daily_money_limit: Option<u256>,
+    pub daily_share_limit: Option<u64>,
+}
+
+#[table(name = transfer, public)]
+pub struct Transfer {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    #[index(btree)]
+    pub sender: Identity,
+    #[index(btree)]
+    pub recipient: Identity,
+    pub kind: TransferKind,
+    pub amount: u256,
+    pub fee: u256,
+    pub timestamp: Timestamp,
+}
+
+pub fn init_transfer_config(
+    ctx: &ReducerContext,
+    money_fee: u16,
+    share_fee: u16,
+    daily_money_limit: Option<u256>,
+    daily_share_limit: Option<u64>,
+) -> Result<(), String> {
+    ctx.db.transfer_config().insert(TransferConfig {
+        money_fee,
+        share_fee,
+        daily_money_limit,
+        daily_share_limit,
+    });
+
+    Ok(())
+}
+
+#[reducer]
+pub fn set_transfer_config(
+    ctx: &ReducerContext,
+    money_fee: u16,
+    share_fee: u16,
+    daily_money_limit: Option<u256>,
+    daily_share_limit: Option<u64>,
+) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    for config in ctx.db.transfer_config().iter() {
+        ctx.db.transfer_config().delete(config);
+    }
+
+    init_transfer_config(ctx, money_fee, share_fee, daily_money_limit, daily_share_limit)
+}
+
+#[reducer]
+pub fn transfer_money(ctx: &ReducerContext, recipient: Identity, amount: u256) -> Result<(), String> {
+    let config = transfer_config(ctx)?;
+
+    if recipient == ctx.sender {
+        return Err("Cannot transfer to yourself.".to_string());
+    }
+
+    if amount == u256::new(0) {
+        return Err("Amount cannot be zero.".to_string());
+    }
+
+    let Some(mut sender) = ctx.db.player().identity().find(ctx.sender) else {
+        return Err("Player not found.".to_string());
+    };
+
+    let Some(mut receiver) = ctx.db.player().identity().find(recipient) else {
+        return Err("Recipient not found.".to_string());
+    };
+
+    if margin_debt(ctx, ctx.sender) > 0 {
+        return Err("Repay your margin loan before transferring money.".to_string());
+    }
+
+    settle_income(ctx, &mut sender);
+    if amount > sender.money {
+        return Err("Not enough money.".to_string());
+    }
+
+    if let Some(limit) = config.daily_money_limit {
+        let sent = recent_transfers(ctx, |kind| kind == TransferKind::Money);
+        if sent.checked_add(amount).is_none_or(|total| total > limit) {
+            return Err("Daily money transfer limit exceeded.".to_string());
+        }
+    }
+
+    let fee = amount
+        .checked_mul(u256::from(config.money_fee))
+        .ok_or("Amount is too large.".to_string())?
+        / u256::from(PRICE_SCALE_FACTOR);
+    let total = amount
+        .checked_add(fee)
+        .ok_or("Amount is too large.".to_string())?;
+    if sender.money < total {
+        return Err("Not enough money.".to_string());
+    }
+
+    sender.money -= total;
+    receiver.money = receiver
+        .money
+        .checked_add(amount)
+        .ok_or("Recipient balance would overflow.".to_string())?;
+
+    revalue_player(ctx, &mut sender);
+    revalue_player(ctx, &mut receiver);
+    ctx.db.player().identity().update(sender);
+    ctx.db.player().identity().update(receiver);
+
+    ctx.db.transfer().insert(Transfer {
+        id: 0,
+        sender: ctx.sender,
+        recipient,
+        kind: TransferKind::Money,
+        amount,
+        fee,
+        timestamp: ctx.timestamp,
+    });
+    Ok(())
+}
+
+#[reducer]
+pub fn transfer_shares(
+    ctx: &ReducerContext,
+    recipient: Identity,
+    stock_id: u16,
+    amount: u64,
+) -> Result<(), String> {
+    let config = transfer_config(ctx)?;
+
+    if recipient == ctx.sender {
+        return Err("Cannot transfer to yourself.".to_string());
+    }
+
+    if amount == 0 {
+        return Err("Amount cannot be zero.".to_string());
+    }
+
+    let Some(mut sender) = ctx.db.player().identity().find(ctx.sender) else {
+        return Err("Player not found.".to_string());
+    };
+
+    let Some(mut receiver) = ctx.db.player().identity().find(recipient) else {
+        return Err("Recipient not found.".to_string());
+    };
+
+    let Some(stock) = ctx.db.stock().id().find(stock_id) else {
+        return Err("Stock not found.".to_string());
+    };
+
+    if margin_debt(ctx, ctx.sender) > 0 {
+        return Err("Repay your margin loan before transferring shares.".to_string());
+    }
+
+    if let Some(limit) = config.daily_share_limit {
+        let sent = recent_transfers(ctx, |kind| matches!(kind, TransferKind::Shares(_)));
+        if sent.saturating_add(u256::from(amount)) > u256::from(limit) {
+            return Err("Daily share transfer limit exceeded.".to_string());
+        }
+    }
+
+    settle_income(ctx, &mut sender);
+
+    let value = u256::from(stock.price_per_share) * u256::from(amount);
+    let fee = value * u256::from(config.share_fee) / u256::from(PRICE_SCALE_FACTOR);
+    if sender.money < fee {
+        return Err("Not enough money to pay the transfer fee.".to_string());
+    }
+
+    let Some(position) = sender.stocks.iter_mut().find(|s| s.stock_id == stock_id) else {
+        return Err("No shares held.".to_string());
+    };
+
+    if position.amount < amount {
+        return Err("Not enough shares held.".to_string());
+    }
+
+    let moved_basis = u128::try_from(
+        u256::from(position.cost_basis) * u256::from(amount) / u256::from(position.amount),
+    )
+    .unwrap_or(position.cost_basis);
+    position.amount -= amount;
+    position.cost_basis -= moved_basis;
+    if position.amount == 0 {
+        sender.stocks.retain(|s| s.stock_id != stock_id);
+    }
+    sender.money -= fee;
+
+    match receiver.stocks.iter_mut().find(|s| s.stock_id == stock_id) {
+        Some(existing) => {
+            existing.amount += amount;
+            existing.cost_basis += moved_basis;
+        }
+        None => receiver.stocks.push(StockType {
+            stock_id,
+            amount,
+            cost_basis: moved_basis,
+            realized_pnl: 0,
+            unrealized_pnl: 0,
+        }),
+    }
+
+    revalue_player(ctx, &mut sender);
+    revalue_player(ctx, &mut receiver);
+    ctx.db.player().identity().update(sender);
+    ctx.db.player().identity().update(receiver);
+
+    ctx.db.transfer().insert(Transfer {
+        id: 0,
+        sender: ctx.sender,
+        recipient,
+        kind: TransferKind::Shares(stock_id),
+        amount: u256::from(amount),
+        fee,
+        timestamp: ctx.timestamp,
+    });
+    Ok(())
+}
+
+fn transfer_config(ctx: &ReducerContext) -> Result<TransferConfig, String> {
+    ctx.db
+        .transfer_config()
+        .iter()
+        .next()
+        .ok_or("Transfer configuration not initialized.".to_string())
+}
+
+/// Total amount the sender has transferred within the rolling limit window.
+fn recent_transfers(ctx: &ReducerContext, matches_kind: impl Fn(TransferKind) -> bool) -> u256 {
+    let window_start = ctx
+        .timestamp
+        .checked_sub(TimeDuration::from_micros(TRANSFER_LIMIT_WINDOW_MICROS))
+        .unwrap_or(ctx.timestamp);
+
+    ctx.db
+        .transfer()
+        .sender()
+        .filter(ctx.sender)
+        .filter(|t| t.timestamp > window_start && matches_kind(t.kind))
+        .fold(u256::new(0), |total, t| total.saturating_add(t.amount))
+}
@@ -1,8 +1,66 @@
-use crate::constants::PRICE_SCALE_FACTOR;
-use crate::player_module::player;
+use crate::achievement_module::{record_stats, total_clicks};
+use crate::admin_module::ensure_admin;
+use crate::constants::{
+    DECIMAL_SCALE_FACTOR, PLAYER_STARTING_CLICK_POWER, PLAYER_STARTING_CLICK_TIMER_MICROS,
+    PLAYER_STARTING_COMBO_MAX_MULTIPLIER, PLAYER_STARTING_COMBO_STEP,
+    PLAYER_STARTING_COMBO_WINDOW_MICROS, PLAYER_STARTING_CRIT_CHANCE, PLAYER_STARTING_CRIT_MULTIPLIER,
+    PLAYER_STARTING_MAX_ORDER_SHARES, PLAYER_STARTING_PASSIVE_INCOME, PLAYER_STARTING_STOCK_BUY_FEE,
+    PLAYER_STARTING_STOCK_SELL_FEE, PRICE_SCALE_FACTOR,
+};
+use crate::player_module::{player, settle_income, OwnedUpgrade, Player};
+use crate::portfolio_module::revalue_player;
+use crate::prestige_module::prestige_record;
+use crate::transaction_module::{OrderType, DEFAULT_ORDER_TYPES};
 use spacetimedb::sats::u256;
-use spacetimedb::{reducer, table, ReducerContext, Table};
+use spacetimedb::{reducer, table, Identity, ReducerContext, SpacetimeType, Table};
 
+/// How the price of the next level grows with the number of levels already owned.
+/// `Exponential` growth is scaled by `DECIMAL_SCALE_FACTOR`; `Custom` lists the cost of
+/// every level explicitly.
+#[derive(SpacetimeType, Clone, PartialEq)]
+pub enum CostCurve {
+    Linear(u128),
+    Exponential(u64),
+    Custom(Vec<u128>),
+}
+
+#[derive(SpacetimeType, Clone, Copy, PartialEq)]
+pub enum PlayerStat {
+    PassiveIncome,
+    ClickPower,
+    ClickTimer,
+    StockBuyFee,
+    StockSellFee,
+    MaxOrderShares,
+    ComboWindow,
+    ComboStep,
+    ComboMaxMultiplier,
+    CritChance,
+    CritMultiplier,
+}
+
+#[derive(SpacetimeType, Clone, Copy, PartialEq)]
+pub enum ModifierKind {
+    Additive,
+    Multiplicative,
+}
+
+#[derive(SpacetimeType, Clone, Copy, PartialEq)]
+pub enum StackingRule {
+    PerLevel,
+    Once,
+}
+
+/// `Additive` values are added to the stat in its own units. `Multiplicative` values are
+/// percentage changes scaled by `DECIMAL_SCALE_FACTOR`, so `1_000` doubles the stat and
+/// `-100` cuts it by 10%. Additive effects apply before multiplicative ones.
+#[derive(SpacetimeType, Clone, Copy, PartialEq)]
+pub struct UpgradeEffect {
+    pub stat: PlayerStat,
+    pub kind: ModifierKind,
+    pub value: i64,
+    pub stacking: StackingRule,
+}
 
 #[table(name = upgrades, public)]
 pub struct Upgrades {
@@ -13,11 +71,16 @@ pub struct Upgrades {
     level: u8,
     cost: u128,
     title: String,
+    #[unique]
     identifier: String,
     description: String,
-    passive_income_bonus: Option<u128>,
-    click_power_bonus: Option<u128>,
-    click_timer_bonus: Option<u64>,
+    effects: Vec<UpgradeEffect>,
+    max_level: u8,
+    cost_curve: CostCurve,
+    required_upgrades: Vec<String>,
+    min_net_worth: u128,
+    min_total_clicks: u64,
+    unlocks_order_types: Vec<OrderType>,
 }
 
 
@@ -27,15 +90,22 @@ pub fn buy_upgrade(ctx: &ReducerContext, upgrade_id: u16) -> Result<(), String>
         return Err("Player not found".to_string());
     };
 
-    if player.upgrades.contains(&upgrade_id) {
-        return Err("Upgrade already owned".to_string());
-    }
-
     let Some(upgrade) = ctx.db.upgrades().id().find(upgrade_id) else {
         return Err("Upgrade not found".to_string());
     };
 
-    let scaled_cost = u256::from(upgrade.cost)
+    let owned = owned_level(&player, upgrade_id);
+    if owned >= upgrade.max_level {
+        return Err("Upgrade already at max level".to_string());
+    }
+
+    if !prerequisites_met(ctx, &player, &upgrade) {
+        return Err("Upgrade prerequisites not met".to_string());
+    }
+
+    settle_income(ctx, &mut player);
+
+    let scaled_cost = u256::from(upgrade_cost(&upgrade, owned)?)
         .checked_mul(u256::from(PRICE_SCALE_FACTOR))
         .ok_or("Overflow calculating upgrade cost.")?;
 
@@ -45,25 +115,24 @@ pub fn buy_upgrade(ctx: &ReducerContext, upgrade_id: u16) -> Result<(), String>
 
     player.money -= scaled_cost;
 
-    if let Some(bonus) = upgrade.passive_income_bonus {
-        player.passive_income += bonus;
-    }
-
-    if let Some(bonus) = upgrade.click_power_bonus {
-        player.click_power += bonus;
+    match player.upgrades.iter_mut().find(|u| u.upgrade_id == upgrade_id) {
+        Some(existing) => existing.level += 1,
+        None => player.upgrades.push(OwnedUpgrade {
+            upgrade_id,
+            level: 1,
+        }),
     }
 
-    if let Some(bonus) = upgrade.click_timer_bonus {
-        player.click_timer = player.click_timer.saturating_sub(bonus as i64);
-    }
-
-    player.upgrades.push(upgrade_id);
-
+    record_stats(ctx, &mut player, |stats| stats.upgrades_bought += 1);
+    recompute_player_stats(ctx, &mut player);
+    revalue_player(ctx, &mut player);
+    refresh_unlockable_upgrades(ctx, &mut player);
     ctx.db.player().identity().update(player);
     Ok(())
 }
 
 #[reducer]
+#[allow(clippy::too_many_arguments)]
 pub fn add_upgrade(
     ctx: &ReducerContext,
     identifier: String,
@@ -71,20 +140,297 @@ pub fn add_upgrade(
     description: String,
     level: u8,
     cost: u128,
-    passive_income_bonus: Option<u128>,
-    click_power_bonus: Option<u128>,
-    click_timer_bonus: Option<u64>,
+    effects: Vec<UpgradeEffect>,
+    max_level: u8,
+    cost_curve: CostCurve,
 ) -> Result<(), String> {
-    ctx.db.upgrades().insert(Upgrades {
+    ensure_admin(ctx)?;
+
+    if max_level == 0 {
+        return Err("Max level cannot be zero.".to_string());
+    }
+
+    if let CostCurve::Custom(costs) = &cost_curve {
+        if costs.len() < max_level as usize {
+            return Err("Custom cost curve must list a cost for every level.".to_string());
+        }
+    }
+
+    ctx.db.upgrades().try_insert(Upgrades {
         id: 0,
         title,
         level,
         cost,
-        passive_income_bonus,
-        click_power_bonus,
-        click_timer_bonus,
+        effects,
         identifier,
         description,
-    });
+        max_level,
+        cost_curve,
+        required_upgrades: Vec::new(),
+        min_net_worth: 0,
+        min_total_clicks: 0,
+        unlocks_order_types: Vec::new(),
+    })
+    .map_err(|_| "Upgrade identifier already exists.".to_string())?;
+    Ok(())
+}
+
+/// `min_net_worth` is unscaled, like `cost`.
+#[reducer]
+pub fn set_upgrade_prerequisites(
+    ctx: &ReducerContext,
+    identifier: String,
+    required_upgrades: Vec<String>,
+    min_net_worth: u128,
+    min_total_clicks: u64,
+) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    let Some(mut upgrade) = ctx.db.upgrades().identifier().find(&identifier) else {
+        return Err("Upgrade not found.".to_string());
+    };
+
+    for required in &required_upgrades {
+        if ctx.db.upgrades().identifier().find(required).is_none() {
+            return Err(format!("Required upgrade '{}' not found.", required));
+        }
+        if requires(ctx, required, &identifier, &mut Vec::new()) {
+            return Err(format!("Requiring '{}' would create a cycle.", required));
+        }
+    }
+
+    upgrade.required_upgrades = required_upgrades;
+    upgrade.min_net_worth = min_net_worth;
+    upgrade.min_total_clicks = min_total_clicks;
+    ctx.db.upgrades().id().update(upgrade);
+    Ok(())
+}
+
+/// Replaces an upgrade's effects and recomputes the stats of every player who owns it.
+#[reducer]
+pub fn set_upgrade_effects(
+    ctx: &ReducerContext,
+    identifier: String,
+    effects: Vec<UpgradeEffect>,
+) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    let Some(mut upgrade) = ctx.db.upgrades().identifier().find(&identifier) else {
+        return Err("Upgrade not found.".to_string());
+    };
+
+    upgrade.effects = effects;
+    let upgrade_id = upgrade.id;
+    ctx.db.upgrades().id().update(upgrade);
+    recompute_owners(ctx, upgrade_id);
     Ok(())
+}
+
+#[reducer]
+pub fn set_upgrade_order_unlocks(
+    ctx: &ReducerContext,
+    identifier: String,
+    unlocks_order_types: Vec<OrderType>,
+) -> Result<(), String> {
+    ensure_admin(ctx)?;
+
+    let Some(mut upgrade) = ctx.db.upgrades().identifier().find(&identifier) else {
+        return Err("Upgrade not found.".to_string());
+    };
+
+    upgrade.unlocks_order_types = unlocks_order_types;
+    let upgrade_id = upgrade.id;
+    ctx.db.upgrades().id().update(upgrade);
+    recompute_owners(ctx, upgrade_id);
+    Ok(())
+}
+
+/// Rebuilds every derived stat from the starting constants plus the effects of all owned
+/// upgrades, so effects can be changed or removed without leaving residue on the row.
+pub fn recompute_player_stats(ctx: &ReducerContext, player: &mut Player) {
+    let effects: Vec<(UpgradeEffect, u8)> = player
+        .upgrades
+        .iter()
+        .filter_map(|owned| {
+            ctx.db
+                .upgrades()
+                .id()
+                .find(owned.upgrade_id)
+                .map(|u| (u, owned.level))
+        })
+        .flat_map(|(u, level)| u.effects.into_iter().map(move |e| (e, level)))
+        .chain(prestige_effects(ctx, player.identity).into_iter().map(|e| (e, 1)))
+        .collect();
+
+    let stat = |stat: PlayerStat, base: i128| apply_effects(base, stat, &effects);
+    player.passive_income =
+        u128::try_from(stat(PlayerStat::PassiveIncome, PLAYER_STARTING_PASSIVE_INCOME as i128)).unwrap_or(u128::MAX);
+    player.click_power =
+        u128::try_from(stat(PlayerStat::ClickPower, PLAYER_STARTING_CLICK_POWER as i128)).unwrap_or(u128::MAX);
+    player.click_timer =
+        i64::try_from(stat(PlayerStat::ClickTimer, PLAYER_STARTING_CLICK_TIMER_MICROS as i128)).unwrap_or(i64::MAX);
+    player.stock_buy_fee =
+        u16::try_from(stat(PlayerStat::StockBuyFee, PLAYER_STARTING_STOCK_BUY_FEE as i128)).unwrap_or(u16::MAX);
+    player.stock_sell_fee =
+        u16::try_from(stat(PlayerStat::StockSellFee, PLAYER_STARTING_STOCK_SELL_FEE as i128)).unwrap_or(u16::MAX);
+    player.max_order_shares =
+        u64::try_from(stat(PlayerStat::MaxOrderShares, PLAYER_STARTING_MAX_ORDER_SHARES as i128)).unwrap_or(u64::MAX);
+    player.combo_window =
+        i64::try_from(stat(PlayerStat::ComboWindow, PLAYER_STARTING_COMBO_WINDOW_MICROS as i128)).unwrap_or(i64::MAX);
+    player.combo_step =
+        u64::try_from(stat(PlayerStat::ComboStep, PLAYER_STARTING_COMBO_STEP as i128)).unwrap_or(u64::MAX);
+    player.combo_max_multiplier =
+        u64::try_from(stat(PlayerStat::ComboMaxMultiplier, PLAYER_STARTING_COMBO_MAX_MULTIPLIER as i128)).unwrap_or(u64::MAX);
+    player.crit_chance =
+        u64::try_from(stat(PlayerStat::CritChance, PLAYER_STARTING_CRIT_CHANCE as i128)).unwrap_or(u64::MAX);
+    player.crit_multiplier =
+        u64::try_from(stat(PlayerStat::CritMultiplier, PLAYER_STARTING_CRIT_MULTIPLIER as i128)).unwrap_or(u64::MAX);
+
+    player.unlocked_order_types = DEFAULT_ORDER_TYPES.to_vec();
+    for owned in &player.upgrades {
+        let Some(upgrade) = ctx.db.upgrades().id().find(owned.upgrade_id) else {
+            continue;
+        };
+        for order_type in upgrade.unlocks_order_types {
+            if !player.unlocked_order_types.contains(&order_type) {
+                player.unlocked_order_types.push(order_type);
+            }
+        }
+    }
+}
+
+fn prestige_effects(ctx: &ReducerContext, identity: Identity) -> Vec<UpgradeEffect> {
+    let Some(record) = ctx.db.prestige_record().identity().find(identity) else {
+        return Vec::new();
+    };
+
+    [
+        (PlayerStat::ClickPower, record.click_power_bonus),
+        (PlayerStat::PassiveIncome, record.passive_income_bonus),
+    ]
+    .into_iter()
+    .filter(|(_, bonus)| *bonus > 0)
+    .map(|(stat, bonus)| UpgradeEffect {
+        stat,
+        kind: ModifierKind::Multiplicative,
+        value: i64::try_from(bonus).unwrap_or(i64::MAX),
+        stacking: StackingRule::Once,
+    })
+    .collect()
+}
+
+fn apply_effects(base: i128, stat: PlayerStat, effects: &[(UpgradeEffect, u8)]) -> i128 {
+    let scale = DECIMAL_SCALE_FACTOR as i128;
+    let matching = || effects.iter().filter(|(e, _)| e.stat == stat);
+    let stacks = |effect: &UpgradeEffect, level: u8| match effect.stacking {
+        StackingRule::PerLevel => level as u32,
+        StackingRule::Once => 1,
+    };
+
+    let mut value = base;
+    for (effect, level) in matching().filter(|(e, _)| e.kind == ModifierKind::Additive) {
+        value += effect.value as i128 * stacks(effect, *level) as i128;
+    }
+    for (effect, level) in matching().filter(|(e, _)| e.kind == ModifierKind::Multiplicative) {
+        for _ in 0..stacks(effect, *level) {
+            value = value * (scale + effect.value as i128) / scale;
+        }
+    }
+
+    value.max(0)
+}
+
+fn recompute_owners(ctx: &ReducerContext, upgrade_id: u16) {
+    for mut player in ctx
+        .db
+        .player()
+        .iter()
+        .filter(|p| owned_level(p, upgrade_id) > 0)
+    {
+        settle_income(ctx, &mut player);
+        recompute_player_stats(ctx, &mut player);
+        revalue_player(ctx, &mut player);
+        ctx.db.player().identity().update(player);
+    }
+}
+
+pub fn refresh_unlockable_upgrades(ctx: &ReducerContext, player: &mut Player) -> bool {
+    let unlockable: Vec<u16> = ctx
+        .db
+        .upgrades()
+        .iter()
+        .filter(|u| owned_level(player, u.id) < u.max_level && prerequisites_met(ctx, player, u))
+        .map(|u| u.id)
+        .collect();
+
+    if player.unlockable_upgrades == unlockable {
+        return false;
+    }
+    player.unlockable_upgrades = unlockable;
+    true
+}
+
+fn prerequisites_met(ctx: &ReducerContext, player: &Player, upgrade: &Upgrades) -> bool {
+    let min_net_worth = u256::from(upgrade.min_net_worth) * u256::from(PRICE_SCALE_FACTOR);
+    if player.net_worth < min_net_worth
+        || total_clicks(ctx, player.identity) < upgrade.min_total_clicks
+    {
+        return false;
+    }
+
+    upgrade.required_upgrades.iter().all(|identifier| {
+        ctx.db
+            .upgrades()
+            .identifier()
+            .find(identifier)
+            .is_some_and(|required| owned_level(player, required.id) > 0)
+    })
+}
+
+/// Whether `from` depends on `target`, directly or transitively.
+fn requires(ctx: &ReducerContext, from: &String, target: &String, visited: &mut Vec<String>) -> bool {
+    if from == target {
+        return true;
+    }
+    if visited.contains(from) {
+        return false;
+    }
+    visited.push(from.clone());
+
+    let Some(upgrade) = ctx.db.upgrades().identifier().find(from) else {
+        return false;
+    };
+    upgrade
+        .required_upgrades
+        .iter()
+        .any(|next| requires(ctx, next, target, visited))
+}
+
+pub fn owned_level(player: &Player, upgrade_id: u16) -> u8 {
+    player
+        .upgrades
+        .iter()
+        .find(|u| u.upgrade_id == upgrade_id)
+        .map_or(0, |u| u.level)
+}
+
+/// Unscaled cost of the next level when `owned` levels are already held.
+fn upgrade_cost(upgrade: &Upgrades, owned: u8) -> Result<u128, String> {
+    let overflow = || "Overflow calculating upgrade cost.".to_string();
+
+    match &upgrade.cost_curve {
+        CostCurve::Linear(step) => step
+            .checked_mul(owned as u128)
+            .and_then(|extra| upgrade.cost.checked_add(extra))
+            .ok_or_else(overflow),
+        CostCurve::Exponential(growth) => (0..owned).try_fold(upgrade.cost, |cost, _| {
+            cost.checked_mul(*growth as u128)
+                .map(|c| c / DECIMAL_SCALE_FACTOR)
+                .ok_or_else(overflow)
+        }),
+        CostCurve::Custom(costs) => costs
+            .get(owned as usize)
+            .copied()
+            .ok_or("No cost defined for this level.".to_string()),
+    }
 }
\ No newline at end of file